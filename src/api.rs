@@ -0,0 +1,91 @@
+use serde_json::Value;
+
+use crate::{character_list::MinimalCharacterMap, http::{HttpClient, HttpSettings}, parsed_character::ParsedCharacter, raw_store::{store_raw, RawManifest}, schema_drift::parse_checked, storage::Storage};
+
+/// The public hakush.in Wuthering Waves data API.
+pub const DEFAULT_BASE_URL: &str = "https://api.hakush.in/ww/data";
+
+/// The item catalog material names are resolved against, fetched once per run.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ItemCatalog {
+    /// Item id -> item object; `None` leaves materials as their ids.
+    pub items: Option<Value>,
+    /// Hash of the stored raw catalog, when it was stored.
+    pub hash: Option<String>,
+}
+
+/// Client for the hakush.in data API, with the rate limiting, retries and
+/// response caching given in its [`HttpSettings`].
+#[derive(Debug)]
+pub struct ApiClient {
+    pub(crate) http: HttpClient,
+    pub(crate) base_url: String,
+    pub(crate) lang: String,
+}
+
+impl ApiClient {
+    /// `base_url` is usually [`DEFAULT_BASE_URL`], `lang` a language code like "en".
+    pub fn new(settings: HttpSettings, base_url: &str, lang: &str) -> Self {
+        ApiClient {
+            http: HttpClient::new(settings),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            lang: lang.to_string(),
+        }
+    }
+
+    /// Fetches the roster, keyed by character id.
+    pub async fn fetch_character_list(&self) -> Result<MinimalCharacterMap, String> {
+        let list = self.http.get_json(&format!("{}/character.json", self.base_url)).await?;
+        return serde_json::from_value(list).map_err(|err| format!("Unexpected character list: {err}"));
+    }
+
+    /// Fetches the item catalog in the client's language.
+    pub async fn fetch_items(&self) -> Result<Value, String> {
+        return self.http.get_json(&format!("{}/{}/item.json", self.base_url, self.lang)).await;
+    }
+
+    /// Fetches one character's payload as the API sends it.
+    pub async fn fetch_raw_character(&self, char_id: &str) -> Result<Value, String> {
+        return self.http.get_json(&format!("{}/{}/character/{char_id}.json", self.base_url, self.lang)).await;
+    }
+
+    /// Fetches and parses one character, naming its materials from `catalog`.
+    pub async fn fetch_character(&self, char_id: &str, catalog: &ItemCatalog) -> Result<ParsedCharacter, String> {
+        let raw = self.fetch_raw_character(char_id).await?;
+        return parse_checked(char_id, raw, catalog.items.as_ref());
+    }
+
+    /// Like [`ApiClient::fetch_items`], but a failure is printed and gives `None`.
+    pub async fn get_item_object(&self) -> Option<Value> {
+        match self.fetch_items().await {
+            Ok(items) => Some(items),
+            Err(err) => {
+                //materials fall back to their ids
+                eprintln!("{err}");
+                None
+            },
+        }
+    }
+
+    /// Fetches the item catalog and stores its raw payload in `storage`.
+    pub async fn fetch_item_catalog(&self, storage: &Storage) -> ItemCatalog {
+        let items = self.get_item_object().await;
+        let hash = items.as_ref().and_then(|items| store_raw(storage, items));
+        return ItemCatalog { items, hash };
+    }
+
+    /// Fetches and parses one character, storing the raw payload it was built from
+    /// so it can be reparsed later.
+    pub(crate) async fn character_api_access(&self, storage: &Storage, char_id: &str, catalog: &ItemCatalog) -> Result<(ParsedCharacter, RawManifest), String> {
+        let raw = self.fetch_raw_character(char_id).await?;
+        let hash = store_raw(storage, &raw).unwrap_or_default();
+        let character = parse_checked(char_id, raw, catalog.items.as_ref())?;
+        let manifest = RawManifest {
+            id: character.id,
+            name: character.name.clone(),
+            character: hash,
+            items: catalog.hash.clone(),
+        };
+        return Ok((character, manifest));
+    }
+}
@@ -0,0 +1,750 @@
+use std::{collections::BTreeMap, fs, io::{self, IsTerminal, Write}, process::ExitCode, sync::Arc, time::Duration};
+
+use clap::Parser;
+use crate::cli::{Cli, Command, DiffFormat, ExportFormat, GlobalArgs, ListFormat, EXIT_CHANGED, EXIT_ERROR, EXIT_OK};
+use crate::{
+    api::{ApiClient, ItemCatalog},
+    change_report::{build_report, ChangeReport},
+    character_list::{compare_rosters, find_by_name, group_roster, name_candidates, MinimalCharacter, MinimalCharacterMap, NameMatch, RosterChanges, RosterFilter, RosterGrouping},
+    history::{list_versions, load_version},
+    json_schema::{character_schema, validate, CHARACTER_SCHEMA_FILE},
+    markdown_export::character_markdown,
+    migration::upgrade_storage,
+    material_plan::plan_materials,
+    parsed_character::ParsedCharacter,
+    raw_store::{load_raw, read_manifest, read_manifests, write_manifest, RawManifest},
+    read_and_write_funcs::{diff_characters, read_character_list, read_snapshot, write_character_list_to_file, write_to_file, SaveOutcome},
+    schema_drift::{check_drift, parse_checked},
+    site::build_site,
+    sqlite_export::export_sqlite,
+    storage::Storage,
+    template_export::Template,
+    table_export::{field, material_rows, multiplier_rows, to_delimited, Delimiter, MATERIAL_HEADER, MULTIPLIER_HEADER},
+    watch::{character_event, emit_events, roster_event, CommandSink, EventSink, FileSink, StdoutSink, WatchEvent},
+    webhook::Notifier,
+    wiki_export::character_wikitext,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+/// Runs the command-line tool on the process arguments, returning its exit code.
+pub async fn run() -> ExitCode {
+    let cli = Cli::parse();
+    let storage = Storage::new(&cli.global.out);
+    //Ok(true) means something changed, which gets its own exit code
+    let result = match &cli.command {
+        Command::List { fetch_new, concurrency, rarity, element, weapon, group_by, format } => {
+            let filter = RosterFilter {
+                rarity: *rarity,
+                element: element.clone(),
+                weapon: weapon.clone(),
+            };
+            list_command(&storage, &cli.global, &filter, group_by.map(RosterGrouping::from), *format, *fetch_new, *concurrency).await
+        },
+        Command::Fetch { characters, all, concurrency } => fetch_command(&storage, &cli.global, characters, *all, *concurrency).await,
+        Command::Watch { characters, all, interval, once, concurrency, events_file, events_command } => {
+            let mut sinks: Vec<Box<dyn EventSink>> = vec![Box::new(StdoutSink)];
+            if let Some(path) = events_file {
+                sinks.push(Box::new(FileSink { path: path.clone() }));
+            }
+            if let Some(command) = events_command {
+                sinks.push(Box::new(CommandSink { command: command.clone() }));
+            }
+            watch_command(&storage, &cli.global, characters, *all, Duration::from_secs(*interval), *once, *concurrency, sinks).await
+        },
+        Command::Reparse => reparse_characters(&storage, &cli.global).await,
+        Command::History { character } => history_command(&storage, character),
+        Command::Show { character, version } => show_command(&storage, character, version),
+        Command::Diff { character, from, to, format } => diff_command(&storage, character, from.as_deref(), to, *format),
+        Command::Export { characters, format, output, sqlite, template, with_changes } => match template {
+            Some(template) => template_command(&storage, characters, template, *with_changes, output.as_deref()),
+            None => export_command(&storage, characters, *format, output.as_deref(), sqlite.as_deref()),
+        },
+        Command::Site { output } => site_command(&storage, output),
+        Command::Plan { character, ascended } => plan_command(&storage, character, *ascended),
+        Command::Migrate => migrate_command(&storage),
+        Command::Drift { characters } => drift_command(&storage, characters),
+        Command::Validate { files, print_schema } => validate_command(&storage, files, *print_schema),
+    };
+    match result {
+        Ok(false) => ExitCode::from(EXIT_OK),
+        Ok(true) => ExitCode::from(EXIT_CHANGED),
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(EXIT_ERROR)
+        },
+    }
+}
+
+async fn save_character(storage: &Storage, notifier: &Notifier, character: ParsedCharacter, strict: bool) -> Result<SaveOutcome, String> {
+    let warnings = character.parse_warnings();
+    if !warnings.is_empty() {
+        eprintln!("{} has {} parse warning(s):", character.name, warnings.len());
+        for warning in &warnings {
+            eprintln!("  {warning}");
+        }
+        if strict {
+            return Err(format!("{} not saved (strict mode).", character.name));
+        }
+    }
+    return write_to_file(storage, character, notifier).await;
+}
+
+fn rebuild_from_raw(storage: &Storage, manifest: &RawManifest) -> Result<ParsedCharacter, String> {
+    let Some(raw_character) = load_raw(storage, &manifest.character) else {
+        return Err(format!("Raw data for {} is missing.", manifest.name));
+    };
+    let item_map = manifest.items.as_deref().and_then(|hash| load_raw(storage, hash));
+    return parse_checked(&manifest.name, raw_character, item_map.as_ref());
+}
+
+//regenerate every parsed snapshot from the stored raw payloads, without network access
+async fn reparse_characters(storage: &Storage, global: &GlobalArgs) -> Result<bool, String> {
+    let manifests = read_manifests(storage);
+    if manifests.is_empty() {
+        return Err("No raw data stored.".to_string());
+    }
+    let notifier = global.notifier();
+    let mut changed = false;
+    let mut errors = Vec::<String>::new();
+    for manifest in manifests {
+        match rebuild_from_raw(storage, &manifest) {
+            Ok(character) => match save_character(storage, &notifier, character, global.strict).await {
+                Ok(outcome) => changed |= outcome.is_changed(),
+                Err(err) => errors.push(err),
+            },
+            Err(err) => errors.push(err),
+        }
+    }
+    return finish(changed, errors);
+}
+
+fn finish(changed: bool, errors: Vec<String>) -> Result<bool, String> {
+    if errors.is_empty() {
+        return Ok(changed);
+    }
+    return Err(errors.join("\n"));
+}
+
+fn print_character_list(groups: &BTreeMap<String, Vec<(&String, &MinimalCharacter)>>) {
+    let chars_per_row = 5;
+    for (title, characters) in groups {
+        if !title.is_empty() {
+            println!("{title}:");
+        }
+        let mut count = 0;
+        for (key, value) in characters {
+            print!("{}: {:<15} ", key, value.en);
+            count += 1;
+            if count % chars_per_row == 0 {
+                println!(); //new line after every N characters
+            }
+        }
+        if count % chars_per_row != 0 {
+            println!(); //forcibly switch to new line if total characters isn't a multiple of N
+        }
+    }
+}
+
+
+fn print_character_list_csv(groups: &BTreeMap<String, Vec<(&String, &MinimalCharacter)>>, grouped: bool) {
+    println!("id,name,rarity,element,weapon,release{}", if grouped { ",group" } else { "" });
+    for (title, characters) in groups {
+        for (id, character) in characters {
+            let fields = [
+                id.to_string(),
+                character.en.clone(),
+                character.rarity().map(|rarity| rarity.to_string()).unwrap_or_default(),
+                character.element_name().unwrap_or_default(),
+                character.weapon_name().unwrap_or_default(),
+                character.release().unwrap_or_default(),
+            ];
+            let mut line = fields.iter().map(|value| field(value, Delimiter::Comma)).collect::<Vec<String>>().join(",");
+            if grouped {
+                line.push_str(&format!(",{}", field(title, Delimiter::Comma)));
+            }
+            println!("{line}");
+        }
+    }
+}
+
+//the current roster, for looking names up; it isn't saved, since only `list` should consume the roster changes
+async fn fetch_roster(storage: &Storage, global: &GlobalArgs) -> Result<MinimalCharacterMap, String> {
+    match global.api_client().fetch_character_list().await {
+        Ok(map) => return Ok(map),
+        //the saved roster is still good enough to look names up in
+        Err(err) if global.offline => return read_character_list(storage).ok_or(err),
+        Err(err) => return Err(err),
+    }
+}
+
+//the current roster, saved with how it differs from the one `list` saved before
+async fn get_character_list(storage: &Storage, global: &GlobalArgs) -> Result<(MinimalCharacterMap, RosterChanges), String> {
+    let map = match global.api_client().fetch_character_list().await {
+        Ok(map) => map,
+        //the saved roster is still good enough to look names up in
+        Err(err) if global.offline => return read_character_list(storage).map(|map| (map, RosterChanges::default())).ok_or(err),
+        Err(err) => return Err(err),
+    };
+    let changes = write_character_list_to_file(storage, &map);
+    return Ok((map, changes));
+}
+
+async fn list_command(storage: &Storage, global: &GlobalArgs, filter: &RosterFilter, grouping: Option<RosterGrouping>, format: ListFormat, fetch_new: bool, concurrency: u32) -> Result<bool, String> {
+    let (map, changes) = get_character_list(storage, global).await?;
+    let groups = group_roster(&map, filter, grouping);
+    match format {
+        ListFormat::Grid => print_character_list(&groups),
+        ListFormat::Csv => print_character_list_csv(&groups, grouping.is_some()),
+        ListFormat::Json => {
+            let json = if grouping.is_some() {
+                serde_json::to_string_pretty(&groups.iter().map(|(title, characters)| (title, characters.iter().cloned().collect::<BTreeMap<_, _>>())).collect::<BTreeMap<_, _>>())
+            } else {
+                serde_json::to_string_pretty(&groups.into_values().flatten().collect::<BTreeMap<_, _>>())
+            };
+            println!("{}", json.unwrap_or_default());
+        },
+    }
+    if fetch_new && !changes.added.is_empty() {
+        let ids = changes.added.iter().map(|(id, _)| id.clone()).collect();
+        fetch_ids(storage, global, ids, concurrency).await?;
+    }
+    return Ok(!changes.is_empty());
+}
+
+//ids are used as-is, names are matched case-insensitively, with suggestions for typos
+fn resolve_name(candidates: &[(String, String)], name_or_id: &str) -> Result<Vec<String>, String> {
+    if name_or_id.parse::<i64>().is_ok() {
+        return Ok(vec![name_or_id.to_string()]);
+    }
+    match find_by_name(candidates, name_or_id) {
+        NameMatch::Found(id) => Ok(vec![id]),
+        NameMatch::Ambiguous(matches) => choose_between(name_or_id, &matches),
+        NameMatch::NotFound { suggestions } if suggestions.is_empty() => Err(format!("No character called {name_or_id}.")),
+        NameMatch::NotFound { suggestions } => Err(format!("No character called {name_or_id}. Did you mean {}?", suggestions.join(", "))),
+    }
+}
+
+//asks which of several same-named characters was meant, or lists their ids when there is nobody to ask
+fn choose_between(name: &str, matches: &[(String, String)]) -> Result<Vec<String>, String> {
+    let ids: Vec<String> = matches.iter().map(|(id, _)| id.clone()).collect();
+    if !io::stdin().is_terminal() {
+        return Err(format!("{name} matches several characters, use one of these IDs: {}", ids.join(", ")));
+    }
+    println!("{name} matches several characters:");
+    for (index, (id, character)) in matches.iter().enumerate() {
+        println!("{:>3}: {id} {character}", index + 1);
+    }
+    print!("Choose a number, or 'a' for all: ");
+    let _ = io::stdout().flush();
+    let mut buffer = String::new();
+    if io::stdin().read_line(&mut buffer).is_err() {
+        return Err("No choice made.".to_string());
+    }
+    let choice = buffer.trim();
+    if choice.eq_ignore_ascii_case("a") {
+        return Ok(ids);
+    }
+    match choice.parse::<usize>().ok().and_then(|number| ids.get(number.checked_sub(1)?)) {
+        Some(id) => Ok(vec![id.clone()]),
+        None => Err(format!("{choice} is not one of the choices.")),
+    }
+}
+
+async fn fetch_command(storage: &Storage, global: &GlobalArgs, characters: &[String], all: bool, concurrency: u32) -> Result<bool, String> {
+    if characters.is_empty() && !all {
+        return Err("Nothing to fetch, give some IDs or names, or use --all.".to_string());
+    }
+    let needs_list = all || characters.iter().any(|name| name.parse::<i64>().is_err());
+    let map = if needs_list { fetch_roster(storage, global).await? } else { MinimalCharacterMap::new() };
+    let mut ids = Vec::<String>::new();
+    if all {
+        ids.extend(map.keys().cloned());
+    }
+    let candidates = name_candidates(&map);
+    for name in characters {
+        ids.extend(resolve_name(&candidates, name)?);
+    }
+    return fetch_ids(storage, global, ids, concurrency).await;
+}
+
+async fn fetch_ids(storage: &Storage, global: &GlobalArgs, ids: Vec<String>, concurrency: u32) -> Result<bool, String> {
+    let client = Arc::new(global.api_client());
+    let catalog = Arc::new(client.fetch_item_catalog(storage).await);
+    let (saved, errors) = fetch_characters(storage, global, &client, &catalog, &global.notifier(), ids, concurrency).await;
+    return finish(saved.iter().any(|(_, _, outcome)| outcome.is_changed()), errors);
+}
+
+//fetches and saves characters, returning (id, name, outcome) for each saved one and the errors for the rest
+async fn fetch_characters(storage: &Storage, global: &GlobalArgs, client: &Arc<ApiClient>, catalog: &Arc<ItemCatalog>, notifier: &Notifier, ids: Vec<String>, concurrency: u32) -> (Vec<(i64, String, SaveOutcome)>, Vec<String>) {
+    let mut saved = Vec::<(i64, String, SaveOutcome)>::new();
+    let mut errors = Vec::<String>::new();
+    //characters are fetched concurrently, but saved one at a time as they arrive
+    let permits = Arc::new(Semaphore::new(concurrency as usize));
+    let mut tasks = JoinSet::new();
+    for id in ids {
+        let (client, catalog, permits, storage) = (client.clone(), catalog.clone(), permits.clone(), storage.clone());
+        tasks.spawn(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return (id, Err("Fetching was cancelled.".to_string()));
+            };
+            let fetched = client.character_api_access(&storage, &id, &catalog).await;
+            (id, fetched)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        let (id, fetched) = match joined {
+            Ok(result) => result,
+            Err(err) => {
+                errors.push(err.to_string());
+                continue;
+            },
+        };
+        //offline, a character missing from the cache can still be rebuilt from its raw payload
+        let fetched = match (fetched, global.offline) {
+            (Err(err), true) => match id.parse::<i64>().ok().and_then(|id| read_manifest(storage, id)) {
+                Some(manifest) => rebuild_from_raw(storage, &manifest).map(|character| (character, manifest)),
+                None => Err(err),
+            },
+            (fetched, _) => fetched,
+        };
+        match fetched {
+            Ok((character, manifest)) => {
+                let (id, name) = (character.id, character.name.clone());
+                match save_character(storage, notifier, character, global.strict).await {
+                    Ok(outcome) => {
+                        write_manifest(storage, &manifest);
+                        saved.push((id, name, outcome));
+                    },
+                    Err(err) => errors.push(err),
+                }
+            },
+            Err(err) => errors.push(err),
+        }
+    }
+    return (saved, errors);
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn watch_command(storage: &Storage, global: &GlobalArgs, characters: &[String], all: bool, interval: Duration, once: bool, concurrency: u32, mut sinks: Vec<Box<dyn EventSink>>) -> Result<bool, String> {
+    //names are resolved once, ids don't change between patches
+    let mut watched = Vec::<String>::new();
+    if !characters.is_empty() {
+        let needs_list = characters.iter().any(|name| name.parse::<i64>().is_err());
+        let map = if needs_list { fetch_roster(storage, global).await? } else { MinimalCharacterMap::new() };
+        let candidates = name_candidates(&map);
+        for name in characters {
+            watched.extend(resolve_name(&candidates, name)?);
+        }
+    }
+    let client = Arc::new(global.api_client());
+    let notifier = global.notifier();
+    //the catalog the saved characters were parsed with, so the first poll can already notice new items
+    let mut items_hash = read_manifests(storage).into_iter().find_map(|manifest| manifest.items);
+    let mut roster = read_character_list(storage);
+    let mut changed = false;
+    loop {
+        let events = watch_poll(storage, global, &client, &notifier, &watched, all, &mut items_hash, &mut roster, concurrency).await;
+        changed |= events.iter().any(WatchEvent::is_change);
+        emit_events(&mut sinks, events);
+        if once {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {},
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    return Ok(changed);
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn watch_poll(storage: &Storage, global: &GlobalArgs, client: &Arc<ApiClient>, notifier: &Notifier, watched: &[String], all: bool, items_hash: &mut Option<String>, roster: &mut Option<MinimalCharacterMap>, concurrency: u32) -> Vec<WatchEvent> {
+    let mut events = Vec::<WatchEvent>::new();
+    let map = match fetch_roster(storage, global).await {
+        Ok(map) => {
+            //compared with the previous poll, since characters.json is left for `list` to update
+            if let Some(previous) = roster.as_ref() {
+                events.extend(roster_event(&compare_rosters(previous, &map)));
+            }
+            *roster = Some(map.clone());
+            map
+        },
+        Err(err) => {
+            events.push(WatchEvent::Error { message: err });
+            MinimalCharacterMap::new()
+        },
+    };
+
+    let catalog = Arc::new(client.fetch_item_catalog(storage).await);
+    if let Some(new_hash) = &catalog.hash {
+        if let Some(old_hash) = items_hash.as_ref().filter(|old_hash| *old_hash != new_hash) {
+            events.push(WatchEvent::ItemsChanged { old_hash: old_hash.clone(), new_hash: new_hash.clone() });
+        }
+        *items_hash = Some(new_hash.clone());
+    }
+
+    let ids = if all {
+        map.keys().cloned().collect()
+    } else if watched.is_empty() {
+        storage.stored_ids().iter().map(i64::to_string).collect()
+    } else {
+        watched.to_vec()
+    };
+    let (saved, errors) = fetch_characters(storage, global, client, &catalog, notifier, ids, concurrency).await;
+    for (id, name, outcome) in &saved {
+        events.extend(character_event(*id, name, outcome));
+    }
+    events.extend(errors.into_iter().map(|message| WatchEvent::Error { message }));
+    return events;
+}
+
+//a saved character, by id or by the name it was saved under
+fn resolve_stored(storage: &Storage, name_or_id: &str) -> Result<i64, String> {
+    let mut candidates = Vec::<(String, String)>::new();
+    for (name, ids) in storage.read_index() {
+        candidates.extend(ids.iter().map(|id| (id.to_string(), name.clone())));
+    }
+    let ids = resolve_name(&candidates, name_or_id)?;
+    match ids.as_slice() {
+        [id] => id.parse::<i64>().map_err(|err| err.to_string()),
+        _ => Err(format!("Choose a single character instead of {name_or_id}.")),
+    }
+}
+
+fn history_command(storage: &Storage, character: &str) -> Result<bool, String> {
+    let id = resolve_stored(storage, character)?;
+    let versions = list_versions(storage, id);
+    if versions.is_empty() {
+        return Err(format!("No history for {character}."));
+    }
+    for (index, version) in versions.iter().enumerate() {
+        println!("{:>3}: {version}", index + 1);
+    }
+    return Ok(false);
+}
+
+fn show_command(storage: &Storage, character: &str, version: &str) -> Result<bool, String> {
+    let id = resolve_stored(storage, character)?;
+    let saved = load_version(storage, id, version).ok_or(format!("Version {version} of {character} not found."))?;
+    println!("{}", serde_json::to_string_pretty(&saved).unwrap_or_default());
+    return Ok(false);
+}
+
+fn diff_command(storage: &Storage, character: &str, from: Option<&str>, to: &str, format: DiffFormat) -> Result<bool, String> {
+    let id = resolve_stored(storage, character)?;
+    let version_count = list_versions(storage, id).len();
+    if version_count == 0 {
+        return Err(format!("{character} has no saved versions."));
+    }
+    //without a starting version, compare against the one before the latest
+    let from = match from {
+        Some(from) => from.to_string(),
+        None if version_count < 2 => return Err(format!("{character} has fewer than two saved versions.")),
+        None => (version_count - 1).to_string(),
+    };
+    let from = from.as_str();
+    let old_char = load_version(storage, id, from).ok_or(format!("Version {from} of {character} not found."))?;
+    let new_char = load_version(storage, id, to).ok_or(format!("Version {to} of {character} not found."))?;
+    let Some(differences) = diff_characters(&old_char, &new_char) else {
+        println!("No differences.");
+        return Ok(false);
+    };
+    match format {
+        DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&differences).unwrap_or_default()),
+        DiffFormat::Markdown => print!("{}", build_report(&old_char, &new_char, &differences).to_markdown()),
+        DiffFormat::Text => print!("{}", build_report(&old_char, &new_char, &differences).to_plain_text()),
+    }
+    return Ok(true);
+}
+
+//the stored ids of the given names or ids, or of every saved character when none are given
+fn selected_ids(storage: &Storage, characters: &[String]) -> Result<Vec<i64>, String> {
+    if characters.is_empty() {
+        return Ok(storage.stored_ids());
+    }
+    return characters.iter().map(|name| resolve_stored(storage, name)).collect();
+}
+
+fn export_command(storage: &Storage, characters: &[String], format: ExportFormat, output: Option<&std::path::Path>, sqlite: Option<&std::path::Path>) -> Result<bool, String> {
+    let ids = selected_ids(storage, characters)?;
+    if let Some(path) = sqlite {
+        let snapshots = ids.iter().map(|id| read_snapshot(storage, *id)).collect::<Result<Vec<ParsedCharacter>, String>>()?;
+        //the catalog the characters were last fetched with
+        let items = read_manifests(storage).into_iter().filter(|manifest| ids.contains(&manifest.id)).find_map(|manifest| manifest.items.and_then(|hash| load_raw(storage, &hash)));
+        export_sqlite(path, &snapshots, items.as_ref())?;
+        println!("{} characters exported to {}.", snapshots.len(), path.display());
+        return Ok(false);
+    }
+    if let Some(dir) = output {
+        fs::create_dir_all(dir).map_err(|err| format!("Could not create {}: {err}", dir.display()))?;
+        //json exports carry their contract with them
+        if format == ExportFormat::Json {
+            let path = dir.join(CHARACTER_SCHEMA_FILE);
+            fs::write(&path, serde_json::to_string_pretty(&character_schema()).unwrap_or_default()).map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+        }
+    }
+    let delimiter = match format {
+        ExportFormat::Csv => Some(Delimiter::Comma),
+        ExportFormat::Tsv => Some(Delimiter::Tab),
+        _ => None,
+    };
+    if let Some(delimiter) = delimiter {
+        return export_tables(storage, &ids, delimiter, output);
+    }
+    for id in ids {
+        let character = read_snapshot(storage, id)?;
+        let (text, extension) = match format {
+            ExportFormat::Json => (serde_json::to_string_pretty(&character).unwrap_or_default(), "json"),
+            ExportFormat::Markdown => (character_markdown(&character), "md"),
+            ExportFormat::Wiki => (character_wikitext(&character), "wiki"),
+            ExportFormat::Csv | ExportFormat::Tsv => unreachable!("tables are exported by export_tables"),
+        };
+        match output {
+            Some(dir) => {
+                let path = dir.join(format!("{id}.{extension}"));
+                fs::write(&path, text).map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+                println!("{} exported to {}.", character.name, path.display());
+            },
+            None => println!("{text}"),
+        }
+    }
+    return Ok(false);
+}
+
+fn template_command(storage: &Storage, characters: &[String], template: &str, with_changes: bool, output: Option<&std::path::Path>) -> Result<bool, String> {
+    let template = Template::load(template)?;
+    let ids = selected_ids(storage, characters)?;
+    if let Some(dir) = output {
+        fs::create_dir_all(dir).map_err(|err| format!("Could not create {}: {err}", dir.display()))?;
+    }
+    for id in ids {
+        let character = read_snapshot(storage, id)?;
+        let changes = if with_changes { latest_report(storage, id) } else { None };
+        let text = template.render(&character, changes.as_ref())?;
+        match output {
+            Some(dir) => {
+                let path = dir.join(format!("{id}.{}", template.extension));
+                fs::write(&path, text).map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+                println!("{} exported to {}.", character.name, path.display());
+            },
+            None => println!("{text}"),
+        }
+    }
+    return Ok(false);
+}
+
+//checks stored raw payloads against the format the parsers know, without fetching anything
+fn drift_command(storage: &Storage, characters: &[String]) -> Result<bool, String> {
+    let ids = characters.iter().map(|name| resolve_stored(storage, name)).collect::<Result<Vec<i64>, String>>()?;
+    let manifests: Vec<RawManifest> = read_manifests(storage).into_iter().filter(|manifest| ids.is_empty() || ids.contains(&manifest.id)).collect();
+    if manifests.is_empty() {
+        return Err("No raw data stored.".to_string());
+    }
+    let mut drifted = false;
+    let mut errors = Vec::<String>::new();
+    for manifest in manifests {
+        let Some(payload) = load_raw(storage, &manifest.character) else {
+            errors.push(format!("Raw data for {} is missing.", manifest.name));
+            continue;
+        };
+        let report = check_drift(&payload);
+        if report.is_empty() {
+            println!("{}: matches the known format", manifest.name);
+            continue;
+        }
+        drifted = true;
+        let verdict = if report.is_breaking() { "would not parse" } else { "parses, ignoring new fields" };
+        println!("{}: {verdict}\n{}", manifest.name, report.to_plain_text());
+    }
+    return finish(drifted, errors);
+}
+
+fn migrate_command(storage: &Storage) -> Result<bool, String> {
+    let (total, upgraded, errors) = upgrade_storage(storage);
+    if total == 0 {
+        return Err("No snapshots saved.".to_string());
+    }
+    println!("{upgraded} of {total} snapshot file(s) upgraded.");
+    return finish(upgraded > 0, errors);
+}
+
+fn validate_command(storage: &Storage, files: &[std::path::PathBuf], print_schema: bool) -> Result<bool, String> {
+    let schema = character_schema();
+    if print_schema {
+        println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default());
+        return Ok(false);
+    }
+    let paths = if files.is_empty() {
+        storage.stored_ids().into_iter().map(|id| storage.snapshot_path(id)).collect()
+    } else {
+        files.to_vec()
+    };
+    if paths.is_empty() {
+        return Err("No snapshots saved.".to_string());
+    }
+    let mut invalid = 0;
+    for path in &paths {
+        let issues = match fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|text| serde_json::from_str(&text).map_err(|err| err.to_string())) {
+            Ok(value) => validate(&schema, &value).into_iter().map(|issue| issue.to_string()).collect(),
+            Err(err) => vec![format!("could not read: {err}")],
+        };
+        if issues.is_empty() {
+            println!("{}: ok", path.display());
+            continue;
+        }
+        invalid += 1;
+        println!("{}: {} problem(s)", path.display(), issues.len());
+        for issue in issues {
+            println!("  {issue}");
+        }
+    }
+    if invalid > 0 {
+        return Err(format!("{invalid} of {} file(s) don't match the schema.", paths.len()));
+    }
+    return Ok(false);
+}
+
+//the report between the last two saved versions, if there are two
+fn latest_report(storage: &Storage, id: i64) -> Option<ChangeReport> {
+    let versions = list_versions(storage, id);
+    let [.., previous, latest] = versions.as_slice() else {
+        return None;
+    };
+    let old_char = load_version(storage, id, previous)?;
+    let new_char = load_version(storage, id, latest)?;
+    let differences = diff_characters(&old_char, &new_char)?;
+    return Some(build_report(&old_char, &new_char, &differences));
+}
+
+//the tables cover every character at once, as multipliers.<ext> and materials.<ext>
+fn export_tables(storage: &Storage, ids: &[i64], delimiter: Delimiter, output: Option<&std::path::Path>) -> Result<bool, String> {
+    let mut multipliers = Vec::<Vec<String>>::new();
+    let mut materials = Vec::<Vec<String>>::new();
+    for id in ids {
+        let character = read_snapshot(storage, *id)?;
+        multipliers.extend(multiplier_rows(&character));
+        materials.extend(material_rows(&character));
+    }
+    let tables = [
+        ("multipliers", to_delimited(&MULTIPLIER_HEADER, &multipliers, delimiter)),
+        ("materials", to_delimited(&MATERIAL_HEADER, &materials, delimiter)),
+    ];
+    match output {
+        Some(dir) => {
+            for (name, text) in tables {
+                let path = dir.join(format!("{name}.{}", delimiter.extension()));
+                fs::write(&path, text).map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+                println!("{} characters exported to {}.", ids.len(), path.display());
+            }
+        },
+        //printed one after the other, separated by a blank line
+        None => println!("{}", tables.map(|(_, text)| text).join("\n")),
+    }
+    return Ok(false);
+}
+
+fn site_command(storage: &Storage, output: &std::path::Path) -> Result<bool, String> {
+    let pages = build_site(storage, output)?;
+    println!("{pages} pages written to {}.", output.display());
+    return Ok(false);
+}
+
+fn plan_command(storage: &Storage, character: &str, ascended: usize) -> Result<bool, String> {
+    let id = resolve_stored(storage, character)?;
+    let saved = read_snapshot(storage, id)?;
+    let plan = plan_materials(&saved, ascended);
+    for (title, items) in [("Ascension materials", &plan.ascensions), ("Skill materials", &plan.skills), ("Total", &plan.total())] {
+        println!("{title}:");
+        for (item, count) in items {
+            println!("  {item}: {count}");
+        }
+    }
+    return Ok(false);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{history::record_snapshot, parsed_character::FORMAT_VERSION, raw_store::store_raw, test_server::{stand_in, Request}};
+
+    use super::*;
+
+    fn temp_cli(name: &str, args: &[&str]) -> (Cli, Storage) {
+        let root = std::env::temp_dir().join(format!("hakushin_wuwa_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let cli = Cli::parse_from(["hakushin_wuwa", "--out", root.to_str().unwrap()].iter().chain(args));
+        let storage = Storage::new(&cli.global.out);
+        return (cli, storage);
+    }
+
+    #[tokio::test]
+    async fn reparse_rebuilds_snapshots_from_raw_payloads() {
+        let (cli, storage) = temp_cli("reparse", &["reparse"]);
+        let character: serde_json::Value = serde_json::from_str(include_str!("fixtures/character.raw.json")).unwrap();
+        let items: serde_json::Value = serde_json::from_str(include_str!("fixtures/items.raw.json")).unwrap();
+        let manifest = RawManifest {
+            id: 1505,
+            name: "Shorekeeper".to_string(),
+            character: store_raw(&storage, &character).unwrap(),
+            items: store_raw(&storage, &items),
+        };
+        write_manifest(&storage, &manifest);
+
+        assert_eq!(reparse_characters(&storage, &cli.global).await, Ok(true));
+        let snapshot = read_snapshot(&storage, 1505).unwrap();
+        assert_eq!(snapshot.name, "Shorekeeper");
+        assert_eq!(snapshot.ascensions["2"]["Topological Confinement"], 3);
+        //nothing changed the second time
+        assert_eq!(reparse_characters(&storage, &cli.global).await, Ok(false));
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+
+    #[tokio::test]
+    async fn only_list_saves_the_roster() {
+        let roster = r#"{"1505": {"en": "Shorekeeper"}, "1506": {"en": "Beta"}}"#;
+        let responses = vec![("200 OK", roster), ("200 OK", include_str!("fixtures/items.raw.json")), ("200 OK", include_str!("fixtures/character.raw.json")), ("200 OK", roster)];
+        let base = stand_in(responses, Arc::new(Mutex::new(Vec::<Request>::new()))).await;
+        let (cli, storage) = temp_cli("roster", &["--api-url", &base, "--no-cache", "--rps", "0", "fetch"]);
+        let saved: MinimalCharacterMap = serde_json::from_str(r#"{"1506": {"en": "Beta"}}"#).unwrap();
+        write_character_list_to_file(&storage, &saved);
+
+        assert_eq!(fetch_command(&storage, &cli.global, &["shorekeeper".to_string()], false, 1).await, Ok(true));
+        assert_eq!(read_character_list(&storage), Some(saved));
+        let (_, changes) = get_character_list(&storage, &cli.global).await.unwrap();
+        assert_eq!(changes.added, vec![("1505".to_string(), "Shorekeeper".to_string())]);
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+
+    #[test]
+    fn exports_report_why_a_snapshot_could_not_be_read() {
+        let (_, storage) = temp_cli("export_newer", &["export"]);
+        fs::create_dir_all(storage.character_dir(1505)).unwrap();
+        let newer = ParsedCharacter { format_version: FORMAT_VERSION + 1, id: 1505, ..ParsedCharacter::default() };
+        fs::write(storage.snapshot_path(1505), serde_json::to_string(&newer).unwrap()).unwrap();
+
+        let err = export_command(&storage, &[], ExportFormat::Json, None, None).unwrap_err();
+        assert!(err.contains("is newer than this tool supports"), "{err}");
+        let err = template_command(&storage, &["1505".to_string()], "discord", false, None).unwrap_err();
+        assert!(err.contains("is newer than this tool supports"), "{err}");
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+
+    #[test]
+    fn diff_compares_the_last_two_versions_and_reports_changes() {
+        let (cli, storage) = temp_cli("diff", &["diff", "Shorekeeper"]);
+        let Command::Diff { character, from, to, format } = &cli.command else { panic!("not a diff: {:?}", cli.command) };
+        let old_char = ParsedCharacter { format_version: FORMAT_VERSION, id: 1505, name: "Shorekeeper".to_string(), rarity: 4, ..Default::default() };
+        record_snapshot(&storage, &old_char, chrono::Utc::now()).unwrap();
+        storage.add_to_index("Shorekeeper", 1505);
+        //a single version can't be compared, and must not underflow
+        let err = diff_command(&storage, character, from.as_deref(), to, *format).unwrap_err();
+        assert!(err.contains("fewer than two"), "{err}");
+
+        record_snapshot(&storage, &ParsedCharacter { rarity: 5, ..old_char.clone() }, chrono::Utc::now() + chrono::Duration::seconds(1)).unwrap();
+        assert_eq!(diff_command(&storage, character, from.as_deref(), to, *format), Ok(true));
+        assert_eq!(diff_command(&storage, character, Some("2"), "latest", DiffFormat::Json), Ok(false));
+        assert!(diff_command(&storage, "Rover", None, "latest", DiffFormat::Text).is_err());
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+use serde_json_diff::{Difference, EntryDifference};
+
+use crate::{numeric_changes::{numeric_changes, NumericChange}, parsed_character::{ParsedCharacter, SkillVariant}};
+
+//a single change found in the diff tree, addressed by its path in the character json
+enum Change {
+    Added(Vec<String>, Value),
+    Removed(Vec<String>),
+    Changed(Vec<String>, Value, Value),
+}
+
+/// A readable summary of how a character changed, as lines like
+/// "Skill 'Origin Calculus' Level 'Stage 1 DMG': 30.5%→32.1%" grouped into sections.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ChangeReport {
+    /// Name of the character that changed.
+    pub character: String,
+    /// Section title -> lines, in the order the sections were first seen.
+    pub sections: Vec<(String, Vec<String>)>,
+}
+
+impl ChangeReport {
+    fn push(&mut self, section: &str, line: String) {
+        match self.sections.iter_mut().find(|(title, _)| title == section) {
+            Some((_, lines)) => lines.push(line),
+            None => self.sections.push((section.to_string(), vec![line])),
+        }
+    }
+
+    /// Whether the report has no lines.
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The report as a Markdown document with a heading per section.
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("## Changes to {}\n", self.character);
+        for (section, lines) in &self.sections {
+            text.push_str(&format!("\n### {section}\n\n"));
+            for line in lines {
+                text.push_str(&format!("- {line}\n"));
+            }
+        }
+        return text;
+    }
+
+    /// The report as indented plain text.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = format!("Changes to {}:\n", self.character);
+        for (section, lines) in &self.sections {
+            text.push_str(&format!("{section}\n"));
+            for line in lines {
+                text.push_str(&format!("  {line}\n"));
+            }
+        }
+        return text;
+    }
+}
+
+/// Describes `differences`, the JSON diff from `old_char` to `new_char`, as a report.
+pub fn build_report(old_char: &ParsedCharacter, new_char: &ParsedCharacter, differences: &Difference) -> ChangeReport {
+    let old_json = json!(old_char);
+    let new_json = json!(new_char);
+    let mut changes = Vec::<Change>::new();
+    collect_changes(differences, &mut Vec::new(), &old_json, &new_json, &mut changes);
+
+    let mut report = ChangeReport {
+        character: new_char.name.clone(),
+        ..Default::default()
+    };
+    //ascension changes are grouped into one line per stage
+    let mut ascension_lines = BTreeMap::<String, Vec<String>>::new();
+
+    for change in changes {
+        let path = change_path(&change).clone();
+        let keys: Vec<&str> = path.iter().map(String::as_str).collect();
+        match keys.as_slice() {
+            ["ascensions", stage, item] => {
+                ascension_lines.entry(stage.to_string()).or_default().push(describe_entry(item, &change));
+            },
+            ["ascensions", stage] => {
+                ascension_lines.entry(stage.to_string()).or_default().push(describe_whole(&change));
+            },
+            ["skills", key, rest @ ..] => {
+                let label = skill_label(old_char, new_char, key);
+                report.push("Skills", format!("{label}{}", describe_skill_change(old_char, new_char, key, rest, &change)));
+            },
+            ["chains", key, rest @ ..] => {
+                let name = new_char.chains.get(*key).or(old_char.chains.get(*key)).map(|chain| chain.name.clone()).unwrap_or_default();
+                let detail = match rest {
+                    [] => describe_whole(&change),
+                    ["Desc"] => "description changed".to_string(),
+                    [field, ..] => describe_entry(field, &change),
+                };
+                report.push("Resonance Chain", format!("Chain {key} '{name}': {detail}"));
+            },
+            ["stats", stat] => {
+                report.push("Stats", describe_entry(stat, &change));
+            },
+            //the tag list is diffed as a single array, so compare it by tag name instead
+            ["tags", ..] => {
+                for line in describe_tags(old_char, new_char) {
+                    report.push("Tags", line);
+                }
+            },
+            [field, ..] => {
+                report.push("General", describe_entry(&title_case(field), &change));
+            },
+            [] => {},
+        }
+    }
+
+    for (stage, lines) in ascension_lines {
+        report.push("Ascensions", format!("Ascension {stage}: {}", lines.join(", ")));
+    }
+
+    //rebalanced numbers go first, since they are what patch summaries care about
+    let numeric_lines: Vec<String> = numeric_changes(old_char, new_char).iter().map(NumericChange::summary).collect();
+    if !numeric_lines.is_empty() {
+        report.sections.insert(0, ("Numeric Changes".to_string(), numeric_lines));
+    }
+    return report;
+}
+
+fn change_path(change: &Change) -> &Vec<String> {
+    match change {
+        Change::Added(path, _) | Change::Removed(path) | Change::Changed(path, _, _) => path,
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    let mut current = value;
+    for key in path {
+        current = match current {
+            Value::Object(map) => map.get(key)?,
+            Value::Array(array) => array.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    return Some(current);
+}
+
+fn collect_changes(difference: &Difference, path: &mut Vec<String>, old_json: &Value, new_json: &Value, changes: &mut Vec<Change>) {
+    let old_value = || lookup(old_json, path).cloned().unwrap_or(Value::Null);
+    let new_value = || lookup(new_json, path).cloned().unwrap_or(Value::Null);
+    match difference {
+        Difference::Object { different_entries } => {
+            for (key, entry) in &different_entries.0 {
+                path.push(key.clone());
+                match entry {
+                    EntryDifference::Missing { value } => {
+                        changes.push(Change::Added(path.clone(), value.clone()));
+                    },
+                    EntryDifference::Extra => {
+                        changes.push(Change::Removed(path.clone()));
+                    },
+                    EntryDifference::Value { value_diff } => {
+                        collect_changes(value_diff, path, old_json, new_json, changes);
+                    },
+                }
+                path.pop();
+            }
+        },
+        Difference::Scalar(_) | Difference::Type { .. } | Difference::Array(_) => {
+            changes.push(Change::Changed(path.clone(), old_value(), new_value()));
+        },
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "nothing".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+//"X added (6)", "X removed" or "X: a→b"
+fn describe_entry(name: &str, change: &Change) -> String {
+    match change {
+        Change::Added(_, value) => format!("{name} added ({})", display(value)),
+        Change::Removed(_) => format!("{name} removed"),
+        Change::Changed(_, old, new) => format!("{name}: {}→{}", display(old), display(new)),
+    }
+}
+
+fn describe_whole(change: &Change) -> String {
+    match change {
+        Change::Added(_, _) => "added".to_string(),
+        Change::Removed(_) => "removed".to_string(),
+        Change::Changed(_, _, _) => "changed".to_string(),
+    }
+}
+
+fn skill_name(character: &ParsedCharacter, key: &str) -> Option<String> {
+    match &character.skills.get(key)?.skill {
+        SkillVariant::SkillS(skill) => Some(skill.name.clone()),
+        SkillVariant::SkillL(skill) => Some(skill.name.clone()),
+        SkillVariant::None => None,
+    }
+}
+
+fn skill_label(old_char: &ParsedCharacter, new_char: &ParsedCharacter, key: &str) -> String {
+    match skill_name(new_char, key).or(skill_name(old_char, key)) {
+        Some(name) => format!("Skill '{name}'"),
+        None => format!("Skill node {key}"),
+    }
+}
+
+fn level_name(character: &ParsedCharacter, skill_key: &str, level_key: &str) -> Option<String> {
+    match &character.skills.get(skill_key)?.skill {
+        SkillVariant::SkillL(skill) => skill.level.get(level_key).map(|level| level.name.clone()),
+        _ => None,
+    }
+}
+
+//the part of a skill line after its label, e.g. " Level 'Stage 1 DMG': 30.5%→32.1%"
+fn describe_skill_change(old_char: &ParsedCharacter, new_char: &ParsedCharacter, key: &str, rest: &[&str], change: &Change) -> String {
+    match rest {
+        [] => format!(" {}", describe_whole(change)),
+        ["Skill", _, "Level", level_key, ..] => {
+            let name = level_name(new_char, key, level_key).or(level_name(old_char, key, level_key)).unwrap_or(level_key.to_string());
+            match (rest.get(4), change) {
+                (Some(&"Format") | None, Change::Changed(_, old, new)) => format!(" Level '{name}': {}→{}", display(old), display(new)),
+                (None, Change::Added(_, value)) => format!(" Level '{name}' added ({})", display(value.get("Format").unwrap_or(&Value::Null))),
+                (None, Change::Removed(_)) => format!(" Level '{name}' removed"),
+                (Some(field), _) => format!(" Level '{name}' {}", describe_entry(field, change)),
+            }
+        },
+        ["Skill", _, "Damage", damage_key, field] => format!(" Damage {damage_key} {}", describe_entry(field, change)),
+        ["Skill", _, "Damage", damage_key] => format!(" Damage {damage_key} {}", describe_whole(change)),
+        ["Skill", _, "Desc"] => " description changed".to_string(),
+        ["Skill", _, "Warnings"] => " parse warnings changed".to_string(),
+        ["Skill", _, field] => format!(" {}", describe_entry(field, change)),
+        ["Skill", ..] => " skill type changed".to_string(),
+        ["Consume", item] => format!(" cost: {}", describe_entry(item, change)),
+        [field, ..] => format!(" {}", describe_entry(field, change)),
+    }
+}
+
+fn describe_tags(old_char: &ParsedCharacter, new_char: &ParsedCharacter) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for tag in &new_char.tags {
+        match old_char.tags.iter().find(|old_tag| old_tag.name == tag.name) {
+            Some(old_tag) if old_tag.desc != tag.desc => lines.push(format!("Tag '{}' description changed", tag.name)),
+            Some(_) => {},
+            None => lines.push(format!("Tag '{}' added", tag.name)),
+        }
+    }
+    for old_tag in &old_char.tags {
+        if !new_char.tags.iter().any(|tag| tag.name == old_tag.name) {
+            lines.push(format!("Tag '{}' removed", old_tag.name));
+        }
+    }
+    if lines.is_empty() {
+        lines.push("Tags reordered".to_string());
+    }
+    return lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parsed_character::{Level, SkillLarge, SkillTree}, read_and_write_funcs::diff_characters};
+
+    use super::*;
+
+    fn character(stage_3: &[(&str, i64)], stage_1_dmg: &str) -> ParsedCharacter {
+        let skill = SkillLarge {
+            name: "Origin Calculus".to_string(),
+            level: BTreeMap::from([("1".to_string(), Level { format: stage_1_dmg.to_string(), name: "Stage 1 DMG".to_string() })]),
+            ..Default::default()
+        };
+        return ParsedCharacter {
+            name: "Shorekeeper".to_string(),
+            skills: BTreeMap::from([("1".to_string(), SkillTree { skill: SkillVariant::SkillL(skill), ..Default::default() })]),
+            ascensions: BTreeMap::from([("3".to_string(), stage_3.iter().map(|(item, count)| (item.to_string(), *count)).collect())]),
+            ..Default::default()
+        };
+    }
+
+    fn report(old_char: &ParsedCharacter, new_char: &ParsedCharacter) -> ChangeReport {
+        return build_report(old_char, new_char, &diff_characters(old_char, new_char).unwrap());
+    }
+
+    fn section<'a>(report: &'a ChangeReport, title: &str) -> &'a [String] {
+        return &report.sections.iter().find(|(section, _)| section == title).unwrap().1;
+    }
+
+    #[test]
+    fn ascension_items_are_described_per_stage() {
+        let old_char = character(&[("Topological Confinement", 4)], "30.5%");
+        let new_char = character(&[("Topological Singularity", 6)], "30.5%");
+        assert_eq!(section(&report(&old_char, &new_char), "Ascensions"), ["Ascension 3: Topological Confinement removed, Topological Singularity added (6)"]);
+    }
+
+    #[test]
+    fn level_values_are_described_by_skill_and_level_name() {
+        let old_char = character(&[], "30.5%");
+        let new_char = character(&[], "32.1%");
+        let report = report(&old_char, &new_char);
+        assert_eq!(section(&report, "Skills"), ["Skill 'Origin Calculus' Level 'Stage 1 DMG': 30.5%→32.1%"]);
+        assert_eq!(report.sections[0].0, "Numeric Changes");
+        assert!(report.to_plain_text().contains("  Skill 'Origin Calculus' Level 'Stage 1 DMG': 30.5%→32.1%\n"));
+        assert!(report.to_markdown().contains("- Skill 'Origin Calculus' Level 'Stage 1 DMG': 30.5%→32.1%\n"));
+    }
+
+    #[test]
+    fn identical_characters_have_no_report() {
+        let old_char = character(&[("Topological Confinement", 4)], "30.5%");
+        assert!(diff_characters(&old_char, &old_char.clone()).is_none());
+    }
+}
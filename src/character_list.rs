@@ -1,9 +1,276 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::game_terms::{element_name, weapon_name};
+
+/// One roster entry. The list endpoint's fields are kept as [`Value`]s, since their types
+/// aren't guaranteed; the methods read them as numbers and names.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MinimalCharacter {
-    pub en: String
+    /// English name.
+    pub en: String,
+    /// Star rating, see [`MinimalCharacter::rarity`].
+    #[serde(default, alias = "rank", skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Value>,
+    /// Element id or name, see [`MinimalCharacter::element_name`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<Value>,
+    /// Weapon type id or name, see [`MinimalCharacter::weapon_name`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<Value>,
+    /// Release date or game version, when the endpoint sends one.
+    #[serde(default, alias = "releaseDate", alias = "ver", skip_serializing_if = "Option::is_none")]
+    pub release: Option<Value>,
+    /// Everything else the endpoint sends, e.g. other languages and icons.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+fn value_as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => text.parse::<i64>().ok(),
+        _ => None,
+    }
+}
+
+//ids are turned into names, names are passed through
+fn value_as_name(value: &Value, name_of: fn(i64) -> Option<&'static str>) -> Option<String> {
+    match value {
+        Value::String(text) => Some(value_as_i64(value).and_then(name_of).map(str::to_string).unwrap_or(text.clone())),
+        Value::Number(_) => value_as_i64(value).map(|id| name_of(id).map(str::to_string).unwrap_or(id.to_string())),
+        _ => None,
+    }
+}
+
+impl MinimalCharacter {
+    /// The star rating, if it is a number.
+    pub fn rarity(&self) -> Option<i64> {
+        self.rarity.as_ref().and_then(value_as_i64)
+    }
+
+    /// The element's name, or its id when the id is unknown.
+    pub fn element_name(&self) -> Option<String> {
+        self.element.as_ref().and_then(|element| value_as_name(element, element_name))
+    }
+
+    /// The weapon type's name, or its id when the id is unknown.
+    pub fn weapon_name(&self) -> Option<String> {
+        self.weapon.as_ref().and_then(|weapon| value_as_name(weapon, weapon_name))
+    }
+
+    /// The release date or version as text.
+    pub fn release(&self) -> Option<String> {
+        match self.release.as_ref()? {
+            Value::String(text) => Some(text.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// The roster, keyed by character id.
+pub type MinimalCharacterMap = BTreeMap<String, MinimalCharacter>;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct RosterFilter {
+    pub rarity: Option<i64>,
+    pub element: Option<String>,
+    pub weapon: Option<String>,
+}
+
+impl RosterFilter {
+    pub fn matches(&self, character: &MinimalCharacter) -> bool {
+        let same = |wanted: &Option<String>, actual: Option<String>| match wanted {
+            Some(wanted) => actual.is_some_and(|actual| actual.eq_ignore_ascii_case(wanted)),
+            None => true,
+        };
+        return self.rarity.is_none_or(|rarity| character.rarity() == Some(rarity))
+            && same(&self.element, character.element_name())
+            && same(&self.weapon, character.weapon_name());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RosterGrouping {
+    Element,
+    Weapon,
+    Rarity,
+}
+
+//group title -> (id, character), with entries the API gave no value for under "Unknown"
+pub fn group_roster<'a>(map: &'a MinimalCharacterMap, filter: &RosterFilter, grouping: Option<RosterGrouping>) -> BTreeMap<String, Vec<(&'a String, &'a MinimalCharacter)>> {
+    let mut groups = BTreeMap::<String, Vec<(&String, &MinimalCharacter)>>::new();
+    for (id, character) in map.iter().filter(|(_, character)| filter.matches(character)) {
+        let title = match grouping {
+            Some(RosterGrouping::Element) => character.element_name(),
+            Some(RosterGrouping::Weapon) => character.weapon_name(),
+            Some(RosterGrouping::Rarity) => character.rarity().map(|rarity| format!("{rarity}-star")),
+            None => Some(String::new()),
+        };
+        groups.entry(title.unwrap_or("Unknown".to_string())).or_default().push((id, character));
+    }
+    return groups;
+}
+
+//names at least this similar to what was typed are offered as suggestions
+const SUGGESTION_THRESHOLD: f64 = 0.75;
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameMatch {
+    Found(String),
+    //several ids share the name, e.g. the Rover variants, as (id, name) pairs
+    Ambiguous(Vec<(String, String)>),
+    NotFound { suggestions: Vec<String> },
 }
 
-pub type MinimalCharacterMap = BTreeMap<String, MinimalCharacter>;
\ No newline at end of file
+//case-insensitive lookup of a name among (id, name) pairs, suggesting close names on a miss
+pub fn find_by_name(candidates: &[(String, String)], query: &str) -> NameMatch {
+    let query = query.trim().to_lowercase();
+    //"Rover" matches every "Rover: <Element>" variant
+    let exact: Vec<(String, String)> = candidates.iter().filter(|(_, name)| {
+        let lower = name.to_lowercase();
+        lower == query || lower.split(':').next().is_some_and(|base| base.trim() == query)
+    }).cloned().collect();
+    match exact.len() {
+        0 => {},
+        1 => return NameMatch::Found(exact[0].0.clone()),
+        _ => return NameMatch::Ambiguous(exact),
+    }
+
+    let mut scored: Vec<(f64, &String)> = candidates.iter().map(|(_, name)| {
+        let lower = name.to_lowercase();
+        //a partial name like "shore" should still point at Shorekeeper
+        let score = if lower.contains(&query) { 1.0 } else { strsim::jaro_winkler(&lower, &query) };
+        (score, name)
+    }).filter(|(score, _)| *score >= SUGGESTION_THRESHOLD).collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+
+    let mut suggestions = Vec::<String>::new();
+    for (_, name) in scored {
+        if !suggestions.contains(name) {
+            suggestions.push(name.clone());
+        }
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    return NameMatch::NotFound { suggestions };
+}
+
+//differences between a saved roster and a freshly fetched one, as (id, name) pairs
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct RosterChanges {
+    pub added: Vec<(String, String)>,
+    pub removed: Vec<(String, String)>,
+    //(id, old name, new name)
+    pub renamed: Vec<(String, String, String)>,
+}
+
+impl RosterChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::<String>::new();
+        for (id, name) in &self.added {
+            lines.push(format!("New character: {id} {name}"));
+        }
+        for (id, name) in &self.removed {
+            lines.push(format!("Removed character: {id} {name}"));
+        }
+        for (id, old_name, new_name) in &self.renamed {
+            lines.push(format!("Renamed character: {id} {old_name} -> {new_name}"));
+        }
+        return lines;
+    }
+}
+
+pub fn compare_rosters(old_map: &MinimalCharacterMap, new_map: &MinimalCharacterMap) -> RosterChanges {
+    let mut changes = RosterChanges::default();
+    for (id, character) in new_map {
+        match old_map.get(id) {
+            Some(old_character) if old_character.en != character.en => {
+                changes.renamed.push((id.clone(), old_character.en.clone(), character.en.clone()));
+            },
+            Some(_) => {},
+            None => changes.added.push((id.clone(), character.en.clone())),
+        }
+    }
+    for (id, old_character) in old_map {
+        if !new_map.contains_key(id) {
+            changes.removed.push((id.clone(), old_character.en.clone()));
+        }
+    }
+    return changes;
+}
+
+pub fn name_candidates(map: &MinimalCharacterMap) -> Vec<(String, String)> {
+    return map.iter().map(|(id, character)| (id.clone(), character.en.clone())).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roster() -> Vec<(String, String)> {
+        let entries = [("1406", "Rover: Aero"), ("1408", "Rover: Aero"), ("1501", "Rover: Spectro"), ("1502", "Rover: Spectro"), ("1505", "Shorekeeper"), ("1604", "Rover: Havoc"), ("1605", "Rover: Havoc")];
+        return entries.iter().map(|(id, name)| (id.to_string(), name.to_string())).collect();
+    }
+
+    fn ids(found: NameMatch) -> Vec<String> {
+        match found {
+            NameMatch::Ambiguous(candidates) => return candidates.into_iter().map(|(id, _)| id).collect(),
+            other => panic!("expected several matches, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rover_matches_every_variant() {
+        assert_eq!(ids(find_by_name(&roster(), "rover")), ["1406", "1408", "1501", "1502", "1604", "1605"]);
+    }
+
+    #[test]
+    fn rover_with_element_matches_its_variants() {
+        assert_eq!(ids(find_by_name(&roster(), "Rover: Havoc")), ["1604", "1605"]);
+        assert_eq!(ids(find_by_name(&roster(), " rover: spectro ")), ["1501", "1502"]);
+    }
+
+    #[test]
+    fn unique_names_are_found_regardless_of_case() {
+        assert_eq!(find_by_name(&roster(), "SHOREKEEPER"), NameMatch::Found("1505".to_string()));
+    }
+
+    #[test]
+    fn typos_get_suggestions() {
+        let NameMatch::NotFound { suggestions } = find_by_name(&roster(), "Rovr: Havok") else {
+            panic!("a misspelt name should not match");
+        };
+        assert_eq!(suggestions.first().map(String::as_str), Some("Rover: Havoc"));
+        assert!(suggestions.len() <= MAX_SUGGESTIONS);
+    }
+
+    #[test]
+    fn roster_fields_are_read_whatever_their_type() {
+        let map: MinimalCharacterMap = serde_json::from_str(r#"{
+            "1505": {"en": "Shorekeeper", "rank": 5, "element": 5, "weapon": 5, "ver": "2.0", "icon": "shorekeeper.webp"},
+            "1304": {"en": "Jinhsi", "rarity": "5", "element": "Spectro", "weapon": "1"},
+            "1102": {"en": "Sanhua", "rarity": 4, "element": 1, "weapon": 2},
+            "1601": {"en": "Taoqi"}
+        }"#).unwrap();
+        let shorekeeper = &map["1505"];
+        assert_eq!((shorekeeper.rarity(), shorekeeper.release().as_deref()), (Some(5), Some("2.0")));
+        assert_eq!(shorekeeper.extra["icon"], "shorekeeper.webp");
+        assert_eq!(map["1304"].weapon_name().as_deref(), Some("Broadblade"));
+
+        let spectro = RosterFilter { rarity: Some(5), element: Some("spectro".to_string()), weapon: None };
+        let names = |groups: BTreeMap<String, Vec<(&String, &MinimalCharacter)>>| -> Vec<(String, Vec<String>)> {
+            return groups.into_iter().map(|(title, entries)| (title, entries.into_iter().map(|(_, character)| character.en.clone()).collect())).collect();
+        };
+        assert_eq!(names(group_roster(&map, &spectro, None)), [(String::new(), vec!["Jinhsi".to_string(), "Shorekeeper".to_string()])]);
+        let by_rarity = names(group_roster(&map, &RosterFilter::default(), Some(RosterGrouping::Rarity)));
+        assert_eq!(by_rarity.iter().map(|(title, entries)| (title.as_str(), entries.len())).collect::<Vec<_>>(), [("4-star", 1), ("5-star", 2), ("Unknown", 1)]);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{api::{ApiClient, DEFAULT_BASE_URL}, character_list::RosterGrouping, http::HttpSettings, http_cache::ResponseCache, storage::Storage, webhook::{Notifier, Webhook, WebhookFormat}};
+
+//exit codes, so scripts can tell "nothing changed" apart from "something changed"
+pub const EXIT_OK: u8 = 0;
+pub const EXIT_ERROR: u8 = 1;
+pub const EXIT_CHANGED: u8 = 2;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Fetches Wuthering Waves character data from hakush.in and tracks changes to it")]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Where snapshots, history and raw payloads are saved
+    #[arg(long, global = true, default_value = ".")]
+    pub out: PathBuf,
+    /// Language of the character and item data
+    #[arg(long, global = true, default_value = "en")]
+    pub lang: String,
+    /// Serve every request from the response cache, without network access
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Seconds a cached response is used before asking the server again
+    #[arg(long, global = true, default_value_t = 0)]
+    pub cache_ttl: u64,
+    /// Neither read nor write the response cache
+    #[arg(long, global = true, conflicts_with = "offline")]
+    pub no_cache: bool,
+    /// Refuse to save characters with unresolved description placeholders
+    #[arg(long, global = true)]
+    pub strict: bool,
+    /// Most requests to send per second (0 for no limit)
+    #[arg(long, global = true, default_value_t = 5.0, value_parser = parse_rps)]
+    pub rps: f64,
+    /// Retries for a request that timed out or got a 429/5xx response
+    #[arg(long, global = true, default_value_t = 4)]
+    pub retries: u32,
+    /// Seconds before a single request times out
+    #[arg(long, global = true, default_value_t = 30)]
+    pub timeout: u64,
+    /// Base URL of the data API
+    #[arg(long, global = true, default_value = DEFAULT_BASE_URL)]
+    pub api_url: String,
+    /// Webhook URL to post a summary of every saved diff to (repeatable)
+    #[arg(long = "webhook", global = true)]
+    pub webhooks: Vec<String>,
+    /// Payload format for the webhooks, by default picked from each URL's host
+    #[arg(long, global = true, value_enum, default_value_t = WebhookKind::Auto)]
+    pub webhook_format: WebhookKind,
+    /// Print webhook payloads instead of sending them
+    #[arg(long, global = true)]
+    pub webhook_dry_run: bool,
+}
+
+fn parse_rps(text: &str) -> Result<f64, String> {
+    let rps = text.parse::<f64>().map_err(|err| err.to_string())?;
+    if !rps.is_finite() || rps < 0.0 {
+        return Err("must be a number of at least 0".to_string());
+    }
+    return Ok(rps);
+}
+
+impl GlobalArgs {
+    pub fn http_settings(&self) -> HttpSettings {
+        HttpSettings {
+            requests_per_second: self.rps,
+            retries: self.retries,
+            timeout: Duration::from_secs(self.timeout),
+            cache: (!self.no_cache).then(|| ResponseCache::new(Storage::new(&self.out).cache_dir())),
+            cache_ttl: Duration::from_secs(self.cache_ttl),
+            offline: self.offline,
+        }
+    }
+
+    pub fn api_client(&self) -> ApiClient {
+        ApiClient::new(self.http_settings(), &self.api_url, &self.lang)
+    }
+
+    pub fn notifier(&self) -> Notifier {
+        let webhooks = self.webhooks.iter().map(|url| Webhook {
+            url: url.clone(),
+            format: match self.webhook_format {
+                WebhookKind::Auto => WebhookFormat::detect(url),
+                WebhookKind::Json => WebhookFormat::Json,
+                WebhookKind::Discord => WebhookFormat::Discord,
+                WebhookKind::Slack => WebhookFormat::Slack,
+            },
+        }).collect();
+        //offline runs never touch the network, so they only show what would be sent
+        return Notifier::new(webhooks, self.webhook_dry_run || self.offline, self.http_settings());
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the character roster and save it to characters.json, reporting new, removed and renamed characters
+    List {
+        /// Fetch snapshots of characters that are new to the roster
+        #[arg(long)]
+        fetch_new: bool,
+        /// Most characters to fetch at the same time
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: u32,
+        /// Only list characters of this rarity
+        #[arg(long)]
+        rarity: Option<i64>,
+        /// Only list characters of this element, e.g. spectro
+        #[arg(long)]
+        element: Option<String>,
+        /// Only list characters using this weapon, e.g. rectifier
+        #[arg(long)]
+        weapon: Option<String>,
+        #[arg(long, value_enum)]
+        group_by: Option<ListGrouping>,
+        #[arg(long, value_enum, default_value_t = ListFormat::Grid)]
+        format: ListFormat,
+    },
+    /// Fetch characters by ID or name and save their snapshots
+    Fetch {
+        characters: Vec<String>,
+        /// Fetch every character in the roster
+        #[arg(long)]
+        all: bool,
+        /// Most characters to fetch at the same time
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: u32,
+    },
+    /// Keep polling the roster, items and characters, printing a JSON line for every change
+    Watch {
+        /// Characters to watch by ID or name, by default every saved character
+        characters: Vec<String>,
+        /// Watch every character in the roster, including new ones
+        #[arg(long)]
+        all: bool,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 900, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Poll once and exit
+        #[arg(long)]
+        once: bool,
+        /// Most characters to fetch at the same time
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: u32,
+        /// Also append events to this JSON lines file
+        #[arg(long)]
+        events_file: Option<PathBuf>,
+        /// Also run this shell command for every event, with the event on its stdin
+        #[arg(long)]
+        events_command: Option<String>,
+    },
+    /// Rebuild every saved snapshot from the stored raw payloads
+    Reparse,
+    /// List the saved versions of a character
+    History {
+        character: String,
+    },
+    /// Print a saved version of a character
+    Show {
+        character: String,
+        /// Version name, position from `history`, or "latest"
+        #[arg(default_value = "latest")]
+        version: String,
+    },
+    /// Compare two saved versions of a character (by default the last two)
+    Diff {
+        character: String,
+        from: Option<String>,
+        #[arg(default_value = "latest")]
+        to: String,
+        #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+    },
+    /// Write the latest snapshot of characters in another format (by default every saved character)
+    Export {
+        characters: Vec<String>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// Directory to write to, instead of printing to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Write every exported character, with the item catalog, to this SQLite database instead
+        #[arg(long, conflicts_with_all = ["format", "output"])]
+        sqlite: Option<PathBuf>,
+        /// Render with a template instead: discord, bbcode, docs, or the path to a Jinja2-style template
+        #[arg(long, conflicts_with_all = ["format", "sqlite"])]
+        template: Option<String>,
+        /// Give the template the character's latest change report as `changes`
+        #[arg(long, requires = "template")]
+        with_changes: bool,
+    },
+    /// Build a static HTML site from the saved snapshots and their history
+    Site {
+        /// Directory to write the site to
+        #[arg(long, default_value = "site")]
+        output: PathBuf,
+    },
+    /// Compare stored raw payloads with the format the parsers expect (by default every character with raw data)
+    Drift {
+        characters: Vec<String>,
+    },
+    /// Upgrade every saved snapshot, history version and legacy <Name>.json file to the current format
+    Migrate,
+    /// Check snapshot files against the JSON Schema of the parsed output (by default every saved snapshot)
+    Validate {
+        files: Vec<PathBuf>,
+        /// Print the schema instead of validating
+        #[arg(long, conflicts_with = "files")]
+        print_schema: bool,
+    },
+    /// Total the materials a character needs for ascensions and skills
+    Plan {
+        character: String,
+        /// Ascension stages already completed
+        #[arg(long, default_value_t = 0)]
+        ascended: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListGrouping {
+    Element,
+    Weapon,
+    Rarity,
+}
+
+impl From<ListGrouping> for RosterGrouping {
+    fn from(grouping: ListGrouping) -> Self {
+        match grouping {
+            ListGrouping::Element => RosterGrouping::Element,
+            ListGrouping::Weapon => RosterGrouping::Weapon,
+            ListGrouping::Rarity => RosterGrouping::Rarity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    Grid,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WebhookKind {
+    Auto,
+    Json,
+    Discord,
+    Slack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    /// A guide page with skill, chain and material tables
+    Markdown,
+    /// MediaWiki template invocations for a fan wiki page
+    Wiki,
+    /// Multiplier and material tables covering every exported character
+    Csv,
+    /// Like csv, but tab-separated
+    Tsv,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rps_must_be_finite_and_not_negative() {
+        assert_eq!(parse_rps("2.5"), Ok(2.5));
+        assert_eq!(parse_rps("0"), Ok(0.0));
+        for invalid in ["NaN", "inf", "-1", "fast"] {
+            assert!(parse_rps(invalid).is_err(), "{invalid} should be rejected");
+        }
+        assert!(Cli::try_parse_from(["hakushin_wuwa", "--rps", "NaN", "reparse"]).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+//display names for the numeric ids the API uses
+
+pub fn element_name(element: i64) -> Option<&'static str> {
+    match element {
+        1 => Some("Glacio"),
+        2 => Some("Fusion"),
+        3 => Some("Electro"),
+        4 => Some("Aero"),
+        5 => Some("Spectro"),
+        6 => Some("Havoc"),
+        _ => None,
+    }
+}
+
+pub fn weapon_name(weapon: i64) -> Option<&'static str> {
+    match weapon {
+        1 => Some("Broadblade"),
+        2 => Some("Sword"),
+        3 => Some("Pistols"),
+        4 => Some("Gauntlets"),
+        5 => Some("Rectifier"),
+        _ => None,
+    }
+}
+
+//the name, or the id itself for one this tool doesn't know yet
+pub fn element_label(element: i64) -> String {
+    return element_name(element).map(str::to_string).unwrap_or(element.to_string());
+}
+
+pub fn weapon_label(weapon: i64) -> String {
+    return weapon_name(weapon).map(str::to_string).unwrap_or(weapon.to_string());
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use chrono::{DateTime, Utc};
+
+use crate::{migration::read_character_file, parsed_character::ParsedCharacter, read_and_write_funcs::write_json_atomic, storage::Storage};
+
+//saves a timestamped copy of the character, named after the UTC time it was taken
+pub fn record_snapshot(storage: &Storage, character: &ParsedCharacter, taken_at: DateTime<Utc>) -> Result<(), String> {
+    let dir = storage.history_dir(character.id);
+    fs::create_dir_all(&dir).map_err(|err| format!("Could not create {}: {err}", dir.display()))?;
+    let version = taken_at.format("%Y-%m-%d_%H-%M-%S-%3f").to_string();
+    let path = dir.join(format!("{version}.json"));
+    return write_json_atomic(&path, character).map_err(|err| format!("Could not write {}: {err}", path.display()));
+}
+
+//versions are returned oldest first
+pub fn list_versions(storage: &Storage, id: i64) -> Vec<String> {
+    let mut versions = Vec::<String>::new();
+    let Ok(entries) = fs::read_dir(storage.history_dir(id)) else {
+        return versions;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                versions.push(stem.to_string());
+            }
+        }
+    }
+    versions.sort();
+    return versions;
+}
+
+//accepts a version name, its 1-based position in list_versions, or "latest"
+pub fn resolve_version(storage: &Storage, id: i64, version: &str) -> Option<String> {
+    let versions = list_versions(storage, id);
+    if version == "latest" {
+        return versions.last().cloned();
+    }
+    if versions.iter().any(|v| v == version) {
+        return Some(version.to_string());
+    }
+    let index = version.parse::<usize>().ok()?;
+    return versions.get(index.checked_sub(1)?).cloned();
+}
+
+pub fn load_version(storage: &Storage, id: i64, version: &str) -> Option<ParsedCharacter> {
+    let version = resolve_version(storage, id, version)?;
+    return read_character_file(&storage.history_dir(id).join(format!("{version}.json"))).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::parsed_character::FORMAT_VERSION;
+
+    use super::*;
+
+    #[test]
+    fn versions_are_kept_in_order_and_resolved() {
+        let storage = Storage::new(std::env::temp_dir().join(format!("hakushin_wuwa_history_{}", std::process::id())));
+        let first = ParsedCharacter { format_version: FORMAT_VERSION, id: 1505, name: "Shorekeeper".to_string(), rarity: 4, ..Default::default() };
+        let second = ParsedCharacter { rarity: 5, ..first.clone() };
+        //recorded out of order, listed by when they were taken
+        record_snapshot(&storage, &second, Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap()).unwrap();
+        record_snapshot(&storage, &first, Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap()).unwrap();
+
+        assert_eq!(list_versions(&storage, 1505), ["2024-12-31_23-59-59-000", "2025-01-02_00-00-00-000"]);
+        assert_eq!(resolve_version(&storage, 1505, "latest").as_deref(), Some("2025-01-02_00-00-00-000"));
+        assert_eq!(resolve_version(&storage, 1505, "1").as_deref(), Some("2024-12-31_23-59-59-000"));
+        assert_eq!(resolve_version(&storage, 1505, "0"), None);
+        assert_eq!(resolve_version(&storage, 1505, "3"), None);
+        assert_eq!(load_version(&storage, 1505, "1"), Some(first));
+        assert_eq!(load_version(&storage, 1505, "2025-01-02_00-00-00-000"), Some(second));
+        assert!(list_versions(&storage, 1506).is_empty());
+        fs::remove_dir_all(&storage.root).unwrap();
+    }
+}
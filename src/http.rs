@@ -0,0 +1,277 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::{header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED}, StatusCode};
+use serde_json::Value;
+use tokio::{sync::Mutex, time::{sleep_until, Instant}};
+
+use crate::http_cache::{CacheMeta, CachedResponse, ResponseCache};
+
+//first retry waits this long, doubling for every retry after it
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+//a rate limit slower than this is treated as this, so tiny --rps values can't overflow the schedule
+const MAX_REQUEST_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How requests to the API and to webhooks are sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    /// Most requests to send per second; 0 for no limit.
+    pub requests_per_second: f64,
+    /// Retries for a request that timed out or got a 429/5xx response, with exponential backoff.
+    pub retries: u32,
+    /// Time before a single request is given up on.
+    pub timeout: Duration,
+    /// Where responses are cached for conditional requests; `None` disables caching.
+    pub cache: Option<ResponseCache>,
+    /// Cached responses younger than this are used without asking the server.
+    pub cache_ttl: Duration,
+    /// Serve everything from the cache and never touch the network.
+    pub offline: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            requests_per_second: 5.0,
+            retries: 4,
+            timeout: Duration::from_secs(30),
+            cache: None,
+            cache_ttl: Duration::ZERO,
+            offline: false,
+        }
+    }
+}
+
+//a reqwest client shared by every request in a run, spacing requests out and retrying failures
+#[derive(Debug)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    settings: HttpSettings,
+    next_request: Mutex<Instant>,
+}
+
+impl HttpClient {
+    pub fn new(settings: HttpSettings) -> Self {
+        let client = reqwest::Client::builder().timeout(settings.timeout).build().unwrap_or_default();
+        HttpClient {
+            client,
+            settings,
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    //waits until the rate limit allows another request
+    async fn wait_for_slot(&self) {
+        //0 (or anything that isn't a rate) means no limit
+        let rps = self.settings.requests_per_second;
+        if rps.is_nan() || rps <= 0.0 {
+            return;
+        }
+        let interval = Duration::try_from_secs_f64(1.0 / rps).unwrap_or(MAX_REQUEST_INTERVAL).min(MAX_REQUEST_INTERVAL);
+        let slot = {
+            let mut next_request = self.next_request.lock().await;
+            let slot = (*next_request).max(Instant::now());
+            *next_request = slot + interval;
+            slot
+        };
+        sleep_until(slot).await;
+    }
+
+    //the cached response for `url`; one whose body doesn't parse is evicted, so the request after it is unconditional
+    fn cached(&self, url: &str) -> Option<CachedResponse> {
+        let cache = self.settings.cache.as_ref()?;
+        let cached = cache.get(url)?;
+        if serde_json::from_slice::<Value>(&cached.body).is_err() {
+            eprintln!("The cached response for {url} is damaged, fetching it again.");
+            cache.remove(url);
+            return None;
+        }
+        return Some(cached);
+    }
+
+    pub async fn get_json(&self, url: &str) -> Result<Value, String> {
+        let cached = self.cached(url);
+        if let Some(cached) = &cached {
+            if self.settings.offline || cached.is_fresh(self.settings.cache_ttl) {
+                return parse_body(url, &cached.body);
+            }
+        }
+        if self.settings.offline {
+            return Err(format!("{url} is not cached, so it is unavailable offline."));
+        }
+
+        let mut attempt = 0;
+        loop {
+            self.wait_for_slot().await;
+            let mut request = self.client.get(url);
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.meta.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &cached.meta.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            let (error, retry_after) = match request.send().await {
+                Ok(response) if response.status() == StatusCode::NOT_MODIFIED => {
+                    let Some(cached) = &cached else {
+                        return Err(format!("{url} returned 304 without a cached copy"));
+                    };
+                    if let Some(cache) = &self.settings.cache {
+                        cache.touch(cached);
+                    }
+                    return parse_body(url, &cached.body);
+                },
+                Ok(response) if response.status() == StatusCode::OK => {
+                    let header = |name| response.headers().get(name).and_then(|value: &HeaderValue| value.to_str().ok()).map(str::to_string);
+                    let meta = CacheMeta {
+                        url: url.to_string(),
+                        etag: header(ETAG),
+                        last_modified: header(LAST_MODIFIED),
+                        fetched_at: Utc::now(),
+                    };
+                    let body = response.bytes().await.map_err(|err| format!("Reading {url} failed: {err}"))?;
+                    let value = parse_body(url, &body)?;
+                    if let Some(cache) = &self.settings.cache {
+                        cache.put(&meta, &body);
+                    }
+                    return Ok(value);
+                },
+                Ok(response) if is_retryable(response.status()) => (format!("{url} returned {}", response.status()), retry_after(&response)),
+                Ok(response) => return Err(format!("{url} returned {}", response.status())),
+                Err(err) if err.is_timeout() || err.is_connect() || err.is_request() => (format!("Request to {url} failed: {err}"), None),
+                Err(err) => return Err(format!("Request to {url} failed: {err}")),
+            };
+            if attempt >= self.settings.retries {
+                return Err(error);
+            }
+            self.back_off(&error, attempt, retry_after).await;
+            attempt += 1;
+        }
+    }
+
+    //posts a json body, with the same rate limit and retries as get_json, but never cached
+    pub async fn post_json(&self, url: &str, body: &Value) -> Result<(), String> {
+        if self.settings.offline {
+            return Err(format!("Not posting to {url} while offline."));
+        }
+        let mut attempt = 0;
+        loop {
+            self.wait_for_slot().await;
+            let (error, retry_after) = match self.client.post(url).json(body).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if is_retryable(response.status()) => (format!("{url} returned {}", response.status()), retry_after(&response)),
+                Ok(response) => return Err(format!("{url} returned {}", response.status())),
+                Err(err) if err.is_timeout() || err.is_connect() || err.is_request() => (format!("Request to {url} failed: {err}"), None),
+                Err(err) => return Err(format!("Request to {url} failed: {err}")),
+            };
+            if attempt >= self.settings.retries {
+                return Err(error);
+            }
+            self.back_off(&error, attempt, retry_after).await;
+            attempt += 1;
+        }
+    }
+
+    async fn back_off(&self, error: &str, attempt: u32, retry_after: Option<Duration>) {
+        let backoff = retry_after.unwrap_or((BASE_BACKOFF * 2u32.saturating_pow(attempt)).min(MAX_BACKOFF));
+        eprintln!("{error}, retrying in {:.1}s.", backoff.as_secs_f64());
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    return response.headers().get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs);
+}
+
+fn parse_body(url: &str, body: &[u8]) -> Result<Value, String> {
+    return serde_json::from_slice(body).map_err(|err| format!("{url} did not return JSON: {err}"));
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::{Arc, Mutex}};
+
+    use serde_json::json;
+
+    use crate::test_server::{stand_in, Request};
+
+    use super::*;
+
+    fn cached_client(name: &str) -> (HttpClient, ResponseCache) {
+        let dir = std::env::temp_dir().join(format!("hakushin_wuwa_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ResponseCache::new(dir);
+        let settings = HttpSettings { requests_per_second: 0.0, retries: 0, timeout: Duration::from_secs(5), cache: Some(cache.clone()), ..HttpSettings::default() };
+        return (HttpClient::new(settings), cache);
+    }
+
+    fn stale_meta(url: &str) -> CacheMeta {
+        return CacheMeta { url: url.to_string(), etag: Some("\"v1\"".to_string()), last_modified: None, fetched_at: Utc::now() - chrono::Duration::hours(1) };
+    }
+
+    #[tokio::test]
+    async fn rate_limits_and_server_errors_are_retried_with_backoff() {
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let responses = vec![("429 Too Many Requests\r\nRetry-After: 0", ""), ("503 Service Unavailable", ""), ("200 OK", r#"{"Id": 1505}"#)];
+        let url = format!("{}/character/1505.json", stand_in(responses, requests.clone()).await);
+        let client = HttpClient::new(HttpSettings { requests_per_second: 0.0, retries: 2, timeout: Duration::from_secs(5), ..HttpSettings::default() });
+
+        let started = Instant::now();
+        assert_eq!(client.get_json(&url).await, Ok(json!({ "Id": 1505 })));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        //the 503 had no Retry-After, so the second retry waited out the base backoff
+        assert!(started.elapsed() >= BASE_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn retries_give_up_after_the_limit() {
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let url = format!("{}/character/1505.json", stand_in(vec![("500 Internal Server Error\r\nRetry-After: 0", "")], requests.clone()).await);
+        let client = HttpClient::new(HttpSettings { requests_per_second: 0.0, retries: 1, timeout: Duration::from_secs(5), ..HttpSettings::default() });
+
+        assert_eq!(client.get_json(&url).await, Err(format!("{url} returned 500 Internal Server Error")));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn tiny_rates_do_not_overflow() {
+        let client = HttpClient::new(HttpSettings { requests_per_second: 1e-300, ..HttpSettings::default() });
+        //the first request goes out straight away, only the next one is scheduled
+        tokio::time::timeout(Duration::from_secs(1), client.wait_for_slot()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn not_modified_serves_the_cached_body() {
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let url = format!("{}/character/1505.json", stand_in(vec![("304 Not Modified", "")], requests.clone()).await);
+        let (client, cache) = cached_client("http_304");
+        cache.put(&stale_meta(&url), br#"{"Id": 1505}"#);
+
+        assert_eq!(client.get_json(&url).await, Ok(json!({ "Id": 1505 })));
+        assert_eq!(requests.lock().unwrap()[0].header("if-none-match").as_deref(), Some("\"v1\""));
+        assert!(cache.get(&url).unwrap().is_fresh(Duration::from_secs(60)), "a 304 should renew the cached copy");
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[tokio::test]
+    async fn damaged_cache_entries_are_fetched_again() {
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let url = format!("{}/character/1505.json", stand_in(vec![("200 OK", r#"{"Id": 1505}"#)], requests.clone()).await);
+        let (client, cache) = cached_client("http_damaged");
+        cache.put(&stale_meta(&url), br#"{"Id": 15"#);
+
+        assert_eq!(client.get_json(&url).await, Ok(json!({ "Id": 1505 })));
+        assert_eq!(requests.lock().unwrap()[0].header("if-none-match"), None, "a damaged entry must not be revalidated");
+        assert_eq!(cache.get(&url).unwrap().body, br#"{"Id": 1505}"#);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::read_and_write_funcs::{write_bytes_atomic, write_json_atomic};
+
+//what the server said about a cached response, for conditional requests
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheMeta {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub meta: CacheMeta,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = Utc::now().signed_duration_since(self.meta.fetched_at);
+        return age.to_std().is_ok_and(|age| age < ttl);
+    }
+}
+
+/// Responses saved as `<dir>/<sha256 of url>.body`, with a `.meta.json` beside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseCache {
+    /// The directory the responses are saved in.
+    pub dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// A cache in `dir`, which is created when the first response is saved.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ResponseCache { dir: dir.into() }
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        return (self.dir.join(format!("{key}.body")), self.dir.join(format!("{key}.meta.json")));
+    }
+
+    pub(crate) fn get(&self, url: &str) -> Option<CachedResponse> {
+        let (body_path, meta_path) = self.paths(url);
+        let meta: CacheMeta = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+        let body = fs::read(body_path).ok()?;
+        return Some(CachedResponse { meta, body });
+    }
+
+    pub(crate) fn put(&self, meta: &CacheMeta, body: &[u8]) {
+        let (body_path, meta_path) = self.paths(&meta.url);
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| write_bytes_atomic(&body_path, body))
+            .and_then(|_| write_json_atomic(&meta_path, meta));
+        if let Err(err) = written {
+            eprintln!("Could not cache {}: {err}", meta.url);
+        }
+    }
+
+    pub(crate) fn remove(&self, url: &str) {
+        let (body_path, meta_path) = self.paths(url);
+        let _ = fs::remove_file(meta_path);
+        let _ = fs::remove_file(body_path);
+    }
+
+    //a 304 means the cached body is still current, so only the fetch time moves
+    pub(crate) fn touch(&self, cached: &CachedResponse) {
+        let meta = CacheMeta {
+            fetched_at: Utc::now(),
+            ..cached.meta.clone()
+        };
+        let (_, meta_path) = self.paths(&meta.url);
+        if let Err(err) = write_json_atomic(&meta_path, &meta) {
+            eprintln!("Could not cache {}: {err}", meta.url);
+        }
+    }
+}
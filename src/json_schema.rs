@@ -0,0 +1,232 @@
+use std::fmt;
+
+use schemars::schema_for;
+use serde_json::{Map, Value};
+
+use crate::parsed_character::ParsedCharacter;
+
+//written next to json exports, so consumers of the snapshot files get the contract with them
+pub const CHARACTER_SCHEMA_FILE: &str = "character.schema.json";
+
+/// The JSON Schema of a saved snapshot, generated from [`ParsedCharacter`].
+//fields the structs don't know about are rejected, so a consumer relying on the schema never sees unexpected keys
+pub fn character_schema() -> Value {
+    let mut schema = serde_json::to_value(schema_for!(ParsedCharacter)).unwrap_or_default();
+    close_objects(&mut schema);
+    return schema;
+}
+
+fn close_objects(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            if map.contains_key("properties") && !map.contains_key("additionalProperties") {
+                map.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+            map.values_mut().for_each(close_objects);
+        },
+        Value::Array(values) => values.iter_mut().for_each(close_objects),
+        _ => {},
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    UnknownField,
+    MissingField,
+    WrongType { expected: String, found: String },
+    NotAllowed(String),
+}
+
+//`path` is a JSON pointer to the offending value, e.g. /skills/3/Skill/SkillL/Level
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaIssue {
+    pub path: String,
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for SchemaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        match &self.kind {
+            IssueKind::UnknownField => write!(f, "{path}: unknown field"),
+            IssueKind::MissingField => write!(f, "{path}: missing required field"),
+            IssueKind::WrongType { expected, found } => write!(f, "{path}: expected {expected}, found {found}"),
+            IssueKind::NotAllowed(reason) => write!(f, "{path}: {reason}"),
+        }
+    }
+}
+
+//checks `value` against the subset of JSON Schema that schemars generates, plus minItems:
+//$ref into $defs, type, properties, required, additionalProperties, items, oneOf/anyOf/allOf, enum and const
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaIssue> {
+    let mut issues = Vec::<SchemaIssue>::new();
+    check(schema, schema, value, "", &mut issues);
+    return issues;
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, path: &str, issues: &mut Vec<SchemaIssue>) {
+    let Some(schema) = schema.as_object() else {
+        //`true` accepts anything, `false` nothing
+        if schema == &Value::Bool(false) {
+            issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::NotAllowed("no value is allowed here".to_string()) });
+        }
+        return;
+    };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve(root, reference) {
+            Some(target) => check(root, target, value, path, issues),
+            None => issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::NotAllowed(format!("unresolvable reference {reference}")) }),
+        }
+    }
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let found = type_name(value);
+        //every integer is also a number
+        if !allowed.is_empty() && !allowed.iter().any(|name| *name == found || (*name == "number" && found == "integer")) {
+            issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::WrongType { expected: allowed.join(" or "), found: found.to_string() } });
+            return;
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::NotAllowed(format!("{value} is not one of {}", Value::Array(options.clone()))) });
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::NotAllowed(format!("{value} is not {constant}")) });
+        }
+    }
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for option in all {
+            check(root, option, value, path, issues);
+        }
+    }
+    for keyword in ["oneOf", "anyOf"] {
+        if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+            check_alternatives(root, options, value, path, issues);
+        }
+    }
+    match value {
+        Value::Object(fields) => check_object(root, schema, fields, path, issues),
+        Value::Array(values) => {
+            if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+                if (values.len() as u64) < min_items {
+                    issues.push(SchemaIssue { path: path.to_string(), kind: IssueKind::NotAllowed(format!("expected at least {min_items} item(s), found {}", values.len())) });
+                }
+            }
+            if let Some(items) = schema.get("items") {
+                for (position, item) in values.iter().enumerate() {
+                    check(root, items, item, &format!("{path}/{position}"), issues);
+                }
+            }
+        },
+        _ => {},
+    }
+}
+
+fn check_object(root: &Value, schema: &Map<String, Value>, fields: &Map<String, Value>, path: &str, issues: &mut Vec<SchemaIssue>) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                issues.push(SchemaIssue { path: format!("{path}/{}", escape_pointer(name)), kind: IssueKind::MissingField });
+            }
+        }
+    }
+    for (name, field) in fields {
+        let field_path = format!("{path}/{}", escape_pointer(name));
+        match (properties.and_then(|properties| properties.get(name)), schema.get("additionalProperties")) {
+            (Some(property), _) => check(root, property, field, &field_path, issues),
+            (None, Some(Value::Bool(false))) => issues.push(SchemaIssue { path: field_path, kind: IssueKind::UnknownField }),
+            (None, Some(additional)) => check(root, additional, field, &field_path, issues),
+            (None, None) => {},
+        }
+    }
+}
+
+//a value matching any option is fine; otherwise report the option it came closest to, judged by
+//the fields of the value itself before anything nested, so deep changes don't hide the intended option
+fn check_alternatives(root: &Value, options: &[Value], value: &Value, path: &str, issues: &mut Vec<SchemaIssue>) {
+    let mut closest: Option<((bool, usize, usize), Vec<SchemaIssue>)> = None;
+    for option in options {
+        let mut option_issues = Vec::<SchemaIssue>::new();
+        check(root, option, value, path, &mut option_issues);
+        if option_issues.is_empty() {
+            return;
+        }
+        //an option rejected on the value's type alone is the least likely to be the intended one
+        let type_mismatch = option_issues.iter().any(|issue| issue.path == path && matches!(issue.kind, IssueKind::WrongType { .. }));
+        let shallow = option_issues.iter().filter(|issue| {
+            let field = issue.path.strip_prefix(path).and_then(|rest| rest.strip_prefix('/'));
+            field.is_some_and(|field| !field.contains('/')) && matches!(issue.kind, IssueKind::UnknownField | IssueKind::MissingField)
+        }).count();
+        let score = (type_mismatch, shallow, option_issues.len());
+        if closest.as_ref().is_none_or(|(best, _)| score < *best) {
+            closest = Some((score, option_issues));
+        }
+    }
+    issues.extend(closest.map(|(_, found)| found).unwrap_or_default());
+}
+
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    return root.pointer(pointer);
+}
+
+//JSON pointer escaping for object keys
+fn escape_pointer(key: &str) -> String {
+    return key.replace('~', "~0").replace('/', "~1");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Value {
+        return serde_json::from_str(include_str!("../Shorekeeper.json")).unwrap();
+    }
+
+    #[test]
+    fn saved_snapshots_are_valid() {
+        let schema = character_schema();
+        assert_eq!(validate(&schema, &snapshot()), vec![]);
+        assert_eq!(validate(&schema, &serde_json::to_value(ParsedCharacter::default()).unwrap()), vec![]);
+    }
+
+    #[test]
+    fn invalid_snapshots_are_reported() {
+        let mut value = snapshot();
+        value["rarity"] = Value::from("5");
+        value.as_object_mut().unwrap().remove("name");
+        assert_eq!(validate(&character_schema(), &value), vec![
+            SchemaIssue { path: "/name".to_string(), kind: IssueKind::MissingField },
+            SchemaIssue { path: "/rarity".to_string(), kind: IssueKind::WrongType { expected: "integer".to_string(), found: "string".to_string() } },
+        ]);
+    }
+
+    #[test]
+    fn unknown_field_in_small_skill_is_not_a_missing_large_skill_field() {
+        let mut value = snapshot();
+        value["skills"]["10"]["Skill"]["SkillS"]["Icon"] = Value::from("icon.png");
+        assert_eq!(validate(&character_schema(), &value), vec![
+            SchemaIssue { path: "/skills/10/Skill/SkillS/Icon".to_string(), kind: IssueKind::UnknownField },
+        ]);
+    }
+}
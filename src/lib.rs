@@ -0,0 +1,54 @@
+//! Fetches Wuthering Waves character data from the hakush.in API, parses it into
+//! [`ParsedCharacter`]s and tracks how saved characters change between game versions.
+//!
+//! [`ApiClient`] fetches the roster, single characters and the item catalog, and
+//! [`parse_checked`] turns a raw character payload into the saved form, returning an
+//! error instead of panicking when the payload has drifted from the format it knows.
+//! Everything that touches disk goes through a [`Storage`] rooted at the output directory.
+
+#![warn(missing_docs)]
+#![allow(clippy::needless_return)]
+
+//generated from a sample payload, so not every struct is used by the parsers
+#[allow(dead_code)]
+pub(crate) mod character;
+pub mod parsed_character;
+pub(crate) mod parsing_funcs;
+pub(crate) mod read_and_write_funcs;
+pub(crate) mod character_list;
+pub(crate) mod raw_store;
+pub(crate) mod history;
+pub(crate) mod change_report;
+pub(crate) mod numeric_changes;
+pub(crate) mod storage;
+pub(crate) mod api;
+pub(crate) mod material_plan;
+pub(crate) mod http;
+pub(crate) mod http_cache;
+pub(crate) mod game_terms;
+pub(crate) mod watch;
+pub(crate) mod webhook;
+pub(crate) mod markdown_export;
+pub(crate) mod wiki_export;
+pub(crate) mod site;
+pub(crate) mod table_export;
+pub(crate) mod sqlite_export;
+pub(crate) mod template_export;
+pub(crate) mod json_schema;
+pub(crate) mod migration;
+pub(crate) mod schema_drift;
+mod cli;
+mod app;
+#[cfg(test)]
+mod test_server;
+
+pub use app::run;
+pub use api::{ApiClient, ItemCatalog, DEFAULT_BASE_URL};
+pub use change_report::{build_report, ChangeReport};
+pub use character_list::{MinimalCharacter, MinimalCharacterMap};
+pub use http::HttpSettings;
+pub use http_cache::ResponseCache;
+pub use json_schema::character_schema;
+pub use parsed_character::{ParsedCharacter, SkillTree, SkillVariant};
+pub use schema_drift::{check_drift, parse_checked, DriftReport};
+pub use storage::{NameIndex, Storage};
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use crate::{game_terms::{element_label, weapon_label}, material_plan::plan_materials, parsed_character::{format_at_levels, in_key_order, ParsedCharacter, SkillVariant, FORMAT_LEVELS}};
+
+//a guide page in the layout the guide writers use: header, tags, stats, skills, chains, materials
+pub fn character_markdown(character: &ParsedCharacter) -> String {
+    let mut text = format!("# {}\n\n", character.name);
+    text.push_str(&format!(
+        "**Rarity:** {}★ | **Element:** {} | **Weapon:** {}\n",
+        character.rarity,
+        element_label(character.element),
+        weapon_label(character.weapon),
+    ));
+
+    if !character.tags.is_empty() {
+        text.push_str("\n## Tags\n\n");
+        for tag in &character.tags {
+            text.push_str(&format!("- **{}**: {}\n", tag.name, tag.desc));
+        }
+    }
+
+    text.push_str("\n## Base Stats\n\n| HP | ATK | DEF |\n| --- | --- | --- |\n");
+    text.push_str(&format!("| {} | {} | {} |\n", character.stats.life, character.stats.atk, character.stats.def));
+
+    text.push_str("\n## Skills\n");
+    let mut passives = Vec::<String>::new();
+    for (_, tree) in in_key_order(&character.skills) {
+        match &tree.skill {
+            SkillVariant::SkillL(skill) => {
+                text.push_str(&format!("\n### {}\n\n*{}*\n\n{}\n", skill.name, skill.type_field, skill.desc));
+                if !skill.level.is_empty() {
+                    let [first, last] = FORMAT_LEVELS;
+                    text.push_str(&format!("\n| Level | Lv {first} | Lv {last} |\n| --- | --- | --- |\n"));
+                    for (_, level) in in_key_order(&skill.level) {
+                        let [at_first, at_last] = format_at_levels(&level.format);
+                        text.push_str(&format!("| {} | {} | {} |\n", cell(&level.name), cell(&at_first), cell(&at_last)));
+                    }
+                }
+            },
+            SkillVariant::SkillS(skill) => passives.push(format!("- **{}**: {}\n", skill.name, skill.desc)),
+            SkillVariant::None => {},
+        }
+    }
+    //the small stat nodes of the skill tree
+    if !passives.is_empty() {
+        text.push_str("\n### Stat Bonuses\n\n");
+        text.push_str(&passives.concat());
+    }
+
+    if !character.chains.is_empty() {
+        text.push_str("\n## Resonance Chain\n\n");
+        for (position, (_, chain)) in in_key_order(&character.chains).into_iter().enumerate() {
+            text.push_str(&format!("{}. **{}**: {}\n", position + 1, chain.name, chain.desc));
+        }
+    }
+
+    if !character.ascensions.is_empty() {
+        text.push_str("\n## Ascension Materials\n\n| Stage | Material | Amount |\n| --- | --- | --- |\n");
+        for (stage, items) in in_key_order(&character.ascensions) {
+            for (item, count) in items {
+                text.push_str(&format!("| {stage} | {} | {count} |\n", cell(item)));
+            }
+        }
+    }
+
+    let skill_materials = plan_materials(character, 0).skills;
+    if !skill_materials.is_empty() {
+        text.push_str("\n## Skill Materials\n\n");
+        text.push_str(&material_table(&skill_materials));
+    }
+    return text;
+}
+
+fn material_table(items: &BTreeMap<String, i64>) -> String {
+    let mut table = "| Material | Amount |\n| --- | --- |\n".to_string();
+    for (item, count) in items {
+        table.push_str(&format!("| {} | {count} |\n", cell(item)));
+    }
+    return table;
+}
+
+//a pipe would end the table cell early
+fn cell(text: &str) -> String {
+    return text.replace('|', "\\|");
+}
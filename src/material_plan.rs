@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use crate::parsed_character::{in_key_order, ParsedCharacter};
+
+//item name -> amount needed
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct MaterialPlan {
+    pub ascensions: BTreeMap<String, i64>,
+    pub skills: BTreeMap<String, i64>,
+}
+
+impl MaterialPlan {
+    pub fn total(&self) -> BTreeMap<String, i64> {
+        let mut total = self.ascensions.clone();
+        for (item, count) in &self.skills {
+            *total.entry(item.clone()).or_insert(0) += count;
+        }
+        return total;
+    }
+}
+
+//materials for every ascension stage after `ascended`, plus every skill tree node
+pub fn plan_materials(character: &ParsedCharacter, ascended: usize) -> MaterialPlan {
+    let mut plan = MaterialPlan::default();
+    for (_, items) in in_key_order(&character.ascensions).into_iter().skip(ascended) {
+        for (item, count) in items {
+            *plan.ascensions.entry(item.clone()).or_insert(0) += count;
+        }
+    }
+    for tree in character.skills.values() {
+        for (item, count) in &tree.consume {
+            *plan.skills.entry(item.clone()).or_insert(0) += count;
+        }
+    }
+    return plan;
+}
@@ -0,0 +1,153 @@
+use std::{fmt, fs, path::{Path, PathBuf}};
+
+use serde_json::{Map, Value};
+
+use crate::{parsed_character::{ParsedCharacter, FORMAT_VERSION}, read_and_write_funcs::{lock_path, write_json_atomic}, storage::Storage};
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+//step i upgrades a snapshot from format version i to i + 1, so there is one per version below FORMAT_VERSION
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize] = [unversioned_to_v1];
+
+//snapshots from before versioning already have the version 1 layout, they only lack the field
+fn unversioned_to_v1(_snapshot: &mut Map<String, Value>) -> Result<(), String> {
+    return Ok(());
+}
+
+//why a snapshot file couldn't be loaded; a newer format is kept apart, since the file is
+//fine and must not be replaced by a tool that can't read it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotError {
+    Unreadable(String),
+    NewerFormat(i64),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Unreadable(err) => write!(f, "{err}"),
+            SnapshotError::NewerFormat(version) => write!(f, "format version {version} is newer than this tool supports ({FORMAT_VERSION})"),
+        }
+    }
+}
+
+//brings a snapshot up to FORMAT_VERSION, returning whether anything had to change
+pub fn migrate(snapshot: &mut Value) -> Result<bool, SnapshotError> {
+    let Some(fields) = snapshot.as_object_mut() else {
+        return Err(SnapshotError::Unreadable("not a JSON object".to_string()));
+    };
+    let version = match fields.get("format_version") {
+        None => 0,
+        Some(version) => version.as_i64().ok_or(SnapshotError::Unreadable(format!("format_version {version} is not a number")))?,
+    };
+    if version > FORMAT_VERSION {
+        return Err(SnapshotError::NewerFormat(version));
+    }
+    if version == FORMAT_VERSION {
+        return Ok(false);
+    }
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        step(fields).map_err(|err| SnapshotError::Unreadable(format!("upgrading from format version {from} failed: {err}")))?;
+    }
+    fields.insert("format_version".to_string(), Value::from(FORMAT_VERSION));
+    return Ok(true);
+}
+
+//reads a snapshot file of any supported version, migrating it in memory; only `upgrade_storage`
+//and saving a character write files, so read-only commands never race a concurrent save
+pub fn read_character_file(path: &Path) -> Result<ParsedCharacter, SnapshotError> {
+    return load(path).map(|(character, _)| character);
+}
+
+//the character in the file, and whether it had to be migrated
+fn load(path: &Path) -> Result<(ParsedCharacter, bool), SnapshotError> {
+    let unreadable = |err: String| SnapshotError::Unreadable(err);
+    let text = fs::read_to_string(path).map_err(|err| unreadable(err.to_string()))?;
+    let mut snapshot: Value = serde_json::from_str(&text).map_err(|err| unreadable(err.to_string()))?;
+    let migrated = migrate(&mut snapshot)?;
+    let character = serde_json::from_value::<ParsedCharacter>(snapshot).map_err(|err| unreadable(err.to_string()))?;
+    return Ok((character, migrated));
+}
+
+//rewrites an older file in the current format, holding the lock of `lock_for` like saving does;
+//returns whether the file was upgraded
+fn upgrade_file(path: &Path, lock_for: &Path) -> Result<bool, String> {
+    let _lock = lock_path(lock_for)?;
+    let (character, migrated) = load(path).map_err(|err| err.to_string())?;
+    if !migrated {
+        return Ok(false);
+    }
+    write_json_atomic(path, &character).map_err(|err| format!("could not be written: {err}"))?;
+    eprintln!("{} upgraded to format version {FORMAT_VERSION}.", path.display());
+    return Ok(true);
+}
+
+//upgrades every snapshot file in storage, returning how many there were, how many were upgraded
+//and the ones that failed
+pub fn upgrade_storage(storage: &Storage) -> (usize, usize, Vec<String>) {
+    let files = snapshot_files(storage);
+    let mut upgraded = 0;
+    let mut errors = Vec::<String>::new();
+    for (path, lock_for) in &files {
+        match upgrade_file(path, lock_for) {
+            Ok(true) => upgraded += 1,
+            Ok(false) => {},
+            Err(err) => errors.push(format!("{}: {err}", path.display())),
+        }
+    }
+    return (files.len(), upgraded, errors);
+}
+
+//every snapshot file in storage: current snapshots, their history and pre-id-layout "<Name>.json" files,
+//each with the path whose lock guards it; a character's history shares its snapshot's lock
+fn snapshot_files(storage: &Storage) -> Vec<(PathBuf, PathBuf)> {
+    let mut files = Vec::<(PathBuf, PathBuf)>::new();
+    for id in storage.stored_ids() {
+        let snapshot = storage.snapshot_path(id);
+        if snapshot.exists() {
+            files.push((snapshot.clone(), snapshot.clone()));
+        }
+        if let Ok(entries) = fs::read_dir(storage.history_dir(id)) {
+            let mut versions: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "json")).collect();
+            versions.sort();
+            files.extend(versions.into_iter().map(|version| (version, snapshot.clone())));
+        }
+    }
+    for name in storage.read_index().keys() {
+        let legacy = storage.legacy_snapshot_path(name);
+        if legacy.exists() {
+            files.push((legacy.clone(), legacy));
+        }
+    }
+    return files;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unversioned_snapshots_are_upgraded() {
+        let mut snapshot = json!({ "id": 1505, "name": "Shorekeeper" });
+        assert_eq!(migrate(&mut snapshot), Ok(true));
+        assert_eq!(snapshot["format_version"], json!(FORMAT_VERSION));
+    }
+
+    #[test]
+    fn current_snapshots_are_left_alone() {
+        let mut snapshot = json!({ "format_version": FORMAT_VERSION, "id": 1505 });
+        let before = snapshot.clone();
+        assert_eq!(migrate(&mut snapshot), Ok(false));
+        assert_eq!(snapshot, before);
+    }
+
+    #[test]
+    fn newer_snapshots_are_refused_untouched() {
+        let mut snapshot = json!({ "format_version": FORMAT_VERSION + 1, "id": 1505 });
+        let before = snapshot.clone();
+        assert_eq!(migrate(&mut snapshot), Err(SnapshotError::NewerFormat(FORMAT_VERSION + 1)));
+        assert_eq!(snapshot, before);
+    }
+}
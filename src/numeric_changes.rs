@@ -0,0 +1,179 @@
+use regex::Regex;
+
+use crate::parsed_character::{ParsedCharacter, SkillVariant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Increase,
+    Decrease,
+}
+
+//a number that changed between two snapshots, e.g. a skill multiplier or a base stat
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericChange {
+    pub label: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl NumericChange {
+    pub fn direction(&self) -> Direction {
+        if self.new > self.old {
+            Direction::Increase
+        } else {
+            Direction::Decrease
+        }
+    }
+
+    //None when the old value was 0, since there is nothing to take a percentage of
+    pub fn percent(&self) -> Option<f64> {
+        if self.old == 0.0 {
+            return None;
+        }
+        return Some((self.new - self.old) / self.old.abs() * 100.0);
+    }
+
+    //"Origin Calculus: Stage 3 DMG +8% (30.5→32.9)"
+    pub fn summary(&self) -> String {
+        let delta = match self.percent() {
+            Some(percent) => format!("{}{}%", if percent > 0.0 { "+" } else { "" }, format_number((percent * 10.0).round() / 10.0)),
+            None => match self.direction() {
+                Direction::Increase => "increased".to_string(),
+                Direction::Decrease => "decreased".to_string(),
+            },
+        };
+        return format!("{} {delta} ({}→{})", self.label, format_number(self.old), format_number(self.new));
+    }
+}
+
+fn format_number(number: f64) -> String {
+    let text = format!("{number:.2}");
+    return text.trim_end_matches('0').trim_end_matches('.').to_string();
+}
+
+//numbers in a formatted value, labelled Lv1/Lv10 when they come from a "[a|b]" pair, with their
+//position among the numbers of that level; a side can hold several, as in "[660+3.00%|1313+5.97%] HP"
+fn labelled_numbers(text: &str) -> Vec<(String, usize, f64)> {
+    let number_re = Regex::new(r"[0-9]+(\.[0-9]+)?").unwrap();
+    let mut numbers = Vec::<(String, usize, f64)>::new();
+    let mut seen = [0, 0, 0];
+    for found in number_re.find_iter(text) {
+        let Ok(number) = found.as_str().parse::<f64>() else {
+            continue;
+        };
+        let before = &text[..found.start()];
+        let (label, level) = match (before.rfind('['), before.rfind(']')) {
+            (Some(open), close) if close.is_none_or(|close| close < open) => {
+                if before[open..].contains('|') { ("Lv10", 2) } else { ("Lv1", 1) }
+            },
+            _ => ("", 0),
+        };
+        seen[level] += 1;
+        numbers.push((label.to_string(), seen[level], number));
+    }
+    return numbers;
+}
+
+fn compare_formatted(label: &str, old_text: &str, new_text: &str, changes: &mut Vec<NumericChange>) {
+    let old_numbers = labelled_numbers(old_text);
+    let new_numbers = labelled_numbers(new_text);
+    //a different shape means the text was rewritten rather than rebalanced
+    if old_numbers.len() != new_numbers.len() {
+        return;
+    }
+    //values are only numbered when their level has more than one
+    let several = |level: &str| old_numbers.iter().filter(|(label, _, _)| label == level).count() > 1;
+    for ((level, position, old), (_, _, new)) in old_numbers.iter().zip(new_numbers.iter()) {
+        if old == new {
+            continue;
+        }
+        let suffix = match (level.as_str(), several(level)) {
+            ("", false) => String::new(),
+            ("", true) => format!(" (value {position})"),
+            (level, true) => format!(" ({level}, value {position})"),
+            (level, false) => format!(" ({level})"),
+        };
+        changes.push(NumericChange {
+            label: format!("{label}{suffix}"),
+            old: *old,
+            new: *new,
+        });
+    }
+}
+
+fn compare_number(label: String, old: i64, new: i64, changes: &mut Vec<NumericChange>) {
+    if old != new {
+        changes.push(NumericChange {
+            label,
+            old: old as f64,
+            new: new as f64,
+        });
+    }
+}
+
+//every rebalanced number between two snapshots of the same character
+pub fn numeric_changes(old_char: &ParsedCharacter, new_char: &ParsedCharacter) -> Vec<NumericChange> {
+    let mut changes = Vec::<NumericChange>::new();
+    compare_number("Base HP".to_string(), old_char.stats.life, new_char.stats.life, &mut changes);
+    compare_number("Base ATK".to_string(), old_char.stats.atk, new_char.stats.atk, &mut changes);
+    compare_number("Base DEF".to_string(), old_char.stats.def, new_char.stats.def, &mut changes);
+
+    for (key, new_tree) in &new_char.skills {
+        let Some(old_tree) = old_char.skills.get(key) else {
+            continue;
+        };
+        let (SkillVariant::SkillL(old_skill), SkillVariant::SkillL(new_skill)) = (&old_tree.skill, &new_tree.skill) else {
+            continue;
+        };
+        for (level_key, new_level) in &new_skill.level {
+            if let Some(old_level) = old_skill.level.get(level_key) {
+                let label = format!("{}: {}", new_skill.name, new_level.name);
+                compare_formatted(&label, &old_level.format, &new_level.format, &mut changes);
+            }
+        }
+        for (damage_key, new_damage) in &new_skill.damage {
+            if let Some(old_damage) = old_skill.damage.get(damage_key) {
+                let label = format!("{}: Damage {damage_key}", new_skill.name);
+                compare_formatted(&format!("{label} multiplier"), &old_damage.rate_lv, &new_damage.rate_lv, &mut changes);
+                compare_number(format!("{label} energy"), old_damage.energy, new_damage.energy, &mut changes);
+                compare_number(format!("{label} toughness"), old_damage.tough_lv, new_damage.tough_lv, &mut changes);
+            }
+        }
+    }
+    return changes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(old: &str, new: &str) -> Vec<String> {
+        let mut changes = Vec::<NumericChange>::new();
+        compare_formatted("Skill", old, new, &mut changes);
+        return changes.iter().map(NumericChange::summary).collect();
+    }
+
+    #[test]
+    fn single_pair_is_labelled_by_level() {
+        assert_eq!(labels("[30.50%|60.64%]", "[32.94%|60.64%]"), vec!["Skill (Lv1) +8% (30.5→32.94)"]);
+    }
+
+    #[test]
+    fn several_numbers_per_side_are_numbered_within_their_level() {
+        //Shorekeeper's healing format
+        assert_eq!(
+            labels("[660+3.00%|1313+5.97%] HP", "[700+3.00%|1313+6.50%] HP"),
+            vec!["Skill (Lv1, value 1) +6.1% (660→700)", "Skill (Lv10, value 2) +8.9% (5.97→6.5)"],
+        );
+        //Jinhsi's multi-hit format
+        assert_eq!(
+            labels("[19.61%+9.81%*3|38.99%+19.50%*3]", "[19.61%+10.50%*3|38.99%+19.50%*4]"),
+            vec!["Skill (Lv1, value 2) +7% (9.81→10.5)", "Skill (Lv10, value 3) +33.3% (3→4)"],
+        );
+    }
+
+    #[test]
+    fn reshaped_text_is_not_compared() {
+        assert!(labels("[660+3.00%|1313+5.97%] HP", "[660|1313] HP").is_empty());
+    }
+}
@@ -1,125 +1,241 @@
-use crate::BTreeMap;
+//! The saved form of a character, as written to its snapshot file.
+
+use std::collections::BTreeMap;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The skill levels a formatted value "[a|b]" shows: a is at level 1 and b at level 10.
+pub const FORMAT_LEVELS: [i64; 2] = [1, 10];
+
+/// Splits a formatted value like "[130+0.60%|259+1.20%] HP" into its forms at [`FORMAT_LEVELS`].
+pub fn format_at_levels(format: &str) -> [String; 2] {
+    let re = Regex::new(r"\[([^|\]]*)\|([^\]]*)\]").unwrap();
+    return [re.replace_all(format, "${1}").to_string(), re.replace_all(format, "${2}").to_string()];
+}
+
+/// The entries of a map keyed by numbers stored as strings, in numeric order.
+//skills, chains and ascensions are keyed like this, so "10" would sort before "2"
+pub fn in_key_order<T>(map: &BTreeMap<String, T>) -> Vec<(&String, &T)> {
+    let mut entries: Vec<(&String, &T)> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| key.parse::<i64>().unwrap_or(i64::MAX));
+    return entries;
+}
+
+/// Version of the snapshot layout written by this build of the tool.
+//bumped whenever the saved layout changes, with a step in migration.rs that upgrades older snapshots
+pub const FORMAT_VERSION: i64 = 1;
+
+/// A character as saved in its snapshot file, with descriptions filled in and
+/// materials named from the item catalog.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ParsedCharacter {
+    /// Layout version of the snapshot, see [`FORMAT_VERSION`].
+    //snapshots saved before versioning have no field, which reads as 0
+    #[serde(default)]
+    pub format_version: i64,
+    /// Character id, as used by the API and in [`Storage`](crate::Storage).
     pub id: i64,
+    /// Name in the language the character was fetched in.
     pub name: String,
+    /// 4 or 5 stars.
     pub rarity: i64,
+    /// Weapon type id: 1 Broadblade, 2 Sword, 3 Pistols, 4 Gauntlets, 5 Rectifier.
     pub weapon: i64,
+    /// Element id: 1 Glacio, 2 Fusion, 3 Electro, 4 Aero, 5 Spectro, 6 Havoc.
     pub element: i64,
+    /// Combat roles shown on the character page.
     pub tags: Vec<ParsedTag>,
+    /// Base stats at level 90.
     pub stats: ParsedStats,
+    /// Skill tree nodes by node number.
     pub skills: BTreeMap<String, SkillTree>,
+    /// Resonance chain nodes by position, "1" to "6".
     pub chains: BTreeMap<String, ParsedChainDescription>, //move params to desc (created new ParsedChainDescription below)
+    /// Materials for each ascension stage: stage -> item name -> count.
     pub ascensions: BTreeMap<String, BTreeMap<String, i64>>
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl ParsedCharacter {
+    /// Every parse warning in the character, prefixed with the skill or chain it came from.
+    pub fn parse_warnings(&self) -> Vec<String> {
+        let mut all_warnings = Vec::<String>::new();
+        for tree in self.skills.values() {
+            let (name, warnings) = match &tree.skill {
+                SkillVariant::SkillS(skill) => (&skill.name, &skill.warnings),
+                SkillVariant::SkillL(skill) => (&skill.name, &skill.warnings),
+                SkillVariant::None => continue,
+            };
+            for warning in warnings {
+                all_warnings.push(format!("Skill '{name}': {warning}"));
+            }
+        }
+        for (key, chain) in &self.chains {
+            for warning in &chain.warnings {
+                all_warnings.push(format!("Chain {key} '{}': {warning}", chain.name));
+            }
+        }
+        return all_warnings;
+    }
+}
+
+/// Base stats, rounded to whole numbers.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ParsedStats {
+    /// Base HP.
     #[serde(rename = "Life")]
     pub life: i64,
+    /// Base ATK.
     #[serde(rename = "Atk")]
     pub atk: i64,
+    /// Base DEF.
     #[serde(rename = "Def")]
     pub def: i64,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A combat role tag, such as "Healing" or "Concerto Regen".
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ParsedTag {
+    /// The tag as shown in game.
     pub name: String,
+    /// What the tag means for this character.
     pub desc: String
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A node of the skill tree and the skill it unlocks.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SkillTree {
+    /// Nodes that must be unlocked before this one.
     #[serde(rename = "ParentNodes")]
     pub parent_nodes: Vec<i64>,
+    /// Kind of node as the API numbers it; stat bonus nodes are 4.
     #[serde(rename = "NodeType")]
     pub node_type: i64,
+    /// Materials to unlock the node and level its skill to the maximum: item name -> count.
     #[serde(rename = "Consume")]
     pub consume: BTreeMap<String, i64>,
+    /// Position of the node in the in-game tree.
     #[serde(rename = "Coordinate")]
     pub coordinate: i64,
+    /// Id of the condition that unlocks the node, 0 for none.
     #[serde(rename = "UnLockCondition")]
     pub un_lock_condition: i64,
+    /// The skill the node unlocks.
     #[serde(rename = "Skill")]
     pub skill: SkillVariant,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The skill a tree node unlocks.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum SkillVariant {
+    /// A passive stat bonus.
     SkillS(SkillSmall),
+    /// A levelled skill with multipliers, such as a Normal Attack or Resonance Liberation.
     SkillL(SkillLarge),
+    /// A node without a skill.
     #[default] None
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A stat bonus node, e.g. "ATK+1.80%".
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SkillSmall {
+    /// Name as shown in game.
     #[serde(rename = "Name")]
     pub name: String,
+    /// Description with its params filled in.
     #[serde(rename = "Desc")]
     pub desc: String, //add params to this
     // #[serde(rename = "Param")]
     // pub param: Vec<String>
+    /// Problems found while filling in the description, such as a placeholder without a param.
+    #[serde(rename = "Warnings", default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A levelled skill, with its per-level values and damage entries.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SkillLarge {
+    /// Name as shown in game.
     #[serde(rename = "Name")]
     pub name: String,
+    /// Description with its params filled in.
     #[serde(rename = "Desc")]
     pub desc: String, //add params to this
     // #[serde(rename = "Param")]
     // pub param: Vec<String>,
+    /// Skill type as shown in game, e.g. "Normal Attack".
     #[serde(rename = "Type")]
     pub type_field: String,
+    /// Rows of the skill's level table by row number.
     #[serde(rename = "Level")]
     pub level: BTreeMap<String, Level>,
+    /// Damage entries by damage id.
     #[serde(rename = "Damage")]
-    pub damage: BTreeMap<String, Damage>
+    pub damage: BTreeMap<String, Damage>,
+    /// Problems found while filling in the description and level table, such as a placeholder without a param.
+    #[serde(rename = "Warnings", default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// One hit or effect of a skill and how it scales.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Damage {
+    /// The stat the damage scales with, e.g. "Atk" or "Life".
     #[serde(rename = "RelatedProperty")]
     pub related_property: String,
+    /// Element id, as in [`ParsedCharacter::element`].
     #[serde(rename = "Element")]
     pub element: i64,
+    /// Elemental gauge the hit applies.
     #[serde(rename = "ElementPower")]
     pub element_power: i64,
+    /// Resonance Energy the hit regains.
     #[serde(rename = "Energy")]
     pub energy: i64,
+    /// How hard the hit interrupts enemies.
     #[serde(rename = "HardnessLv")]
     pub hardness_lv: i64,
+    /// Multiplier at skill levels 1 and 10, as "[a|b]%".
     #[serde(rename = "RateLv")]
     pub rate_lv: String,
+    /// Vibration Strength the hit removes.
     #[serde(rename = "ToughLv")]
     pub tough_lv: i64,
+    /// Kind of hit as the API numbers it.
     #[serde(rename = "Type")]
     pub type_field: i64,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A row of a skill's level table.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Level {
+    /// The row's value with its levels filled in, where "[a|b]" is a at skill level 1 and b at level 10;
+    /// see [`format_at_levels`].
     #[serde(rename = "Format")]
     pub format: String, //move param to here. instead of "None", set this to "{0}"
+    /// What the row holds, e.g. "Stage 1 DMG".
     #[serde(rename = "Name")]
     pub name: String,
     // #[serde(rename = "Param")]
     // pub param: Vec<Vec<String>>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A resonance chain node, with its params filled into the description.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedChainDescription {
+    /// Name as shown in game.
     #[serde(rename = "Name")]
     pub name: String,
+    /// Description with its params filled in.
     #[serde(rename = "Desc")]
-    pub desc: String
+    pub desc: String,
+    /// Problems found while filling in the description, such as a placeholder without a param.
+    #[serde(rename = "Warnings", default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
\ No newline at end of file
@@ -2,7 +2,39 @@ use std::collections::BTreeMap;
 use serde_json::Value;
 use regex::{Captures, Regex};
 
-use crate::{character::{ChainDescription, Consume, N90}, parsed_character::{self, Damage, Level, ParsedChainDescription, ParsedStats, ParsedTag, SkillLarge, SkillSmall, SkillTree, SkillVariant}};
+use crate::{character::{ChainDescription, Character, Consume, N90}, parsed_character::{Damage, Level, ParsedChainDescription, ParsedCharacter, ParsedStats, ParsedTag, SkillLarge, SkillSmall, SkillTree, SkillVariant, FORMAT_VERSION}};
+
+/// Builds the saved form of a character from its raw payload, naming materials
+/// from the item catalog when one is given.
+///
+/// Panics on payloads that don't match the format the parsers know; use
+/// [`parse_checked`](crate::schema_drift::parse_checked) for payloads straight from the API.
+pub fn parse_character(result : Character, item_map : Option<&Value>) -> ParsedCharacter {
+    //convert Value to tags
+    let tags = parse_character_tag(&result.tag);
+    //handle skill tree
+    let new_tree = parse_character_skilltrees(&result.skill_trees, item_map);
+
+    let chains = parse_chains(result.chains);
+
+    let new_ascensions: BTreeMap<String, BTreeMap<String, i64>> = parse_ascensions(item_map, result.ascensions);
+
+    let stats = parse_stats(result.stats.n6.n90);
+
+    return ParsedCharacter {
+        format_version: FORMAT_VERSION,
+        id: result.id,
+        name: result.name,
+        rarity: result.rarity,
+        weapon: result.weapon,
+        element: result.element,
+        tags,
+        stats,
+        skills: new_tree,
+        chains,
+        ascensions: new_ascensions
+    };
+}
 
 pub fn parse_character_tag(tags : &serde_json::Value) -> Vec<ParsedTag>{
     let values = tags.as_object().unwrap().values();
@@ -22,7 +54,7 @@ pub fn parse_character_tag(tags : &serde_json::Value) -> Vec<ParsedTag>{
 }
 
 fn add_or_update_map(map : &mut BTreeMap<i64, i64>, key : &i64, value : &i64){
-    match map.get(&key) {
+    match map.get(key) {
         Some(val) => {
             map.insert(*key, val + value);
         },
@@ -32,7 +64,7 @@ fn add_or_update_map(map : &mut BTreeMap<i64, i64>, key : &i64, value : &i64){
     }
 }
 
-fn consume_costs (consume : &Vec<Value>) -> BTreeMap<i64, i64> {
+fn consume_costs (consume : &[Value]) -> BTreeMap<i64, i64> {
     let mut total_costs = BTreeMap::<i64, i64>::new();
     for con in consume {
         let key = con.get("Key").unwrap().as_i64().unwrap();
@@ -53,7 +85,7 @@ fn parse_format(test_text : Option<&str>) -> String {
     }
 }
 
-fn parse_level (level : &serde_json::Map<String, Value>) -> BTreeMap<std::string::String, Level> {
+fn parse_level (level : &serde_json::Map<String, Value>, warnings: &mut Vec<String>) -> BTreeMap<std::string::String, Level> {
     //enter map
     let mut level_map : BTreeMap<String, Level> = BTreeMap::new();
     for (k, v) in level { 
@@ -64,11 +96,9 @@ fn parse_level (level : &serde_json::Map<String, Value>) -> BTreeMap<std::string
         let mut format : String = parse_format(level_obj.get("Format").unwrap().as_str());
         let name = level_obj.get("Name").unwrap().as_str().unwrap().to_string();
 
-        //first level array
-        let param1 = level_obj.get("Param").unwrap().as_array().unwrap();
-
-        let param_list = param1.get(0).unwrap().as_array().unwrap();
-        format = parse_level_regex(format, param_list);
+        //one array of per-level values for each placeholder
+        let param_lists = level_obj.get("Param").unwrap().as_array().unwrap();
+        format = parse_level_regex(format, param_lists, &name, warnings);
 
         let new_level = Level {
             format,
@@ -102,7 +132,7 @@ fn parse_damage(damage: &serde_json::Map<String, Value>) -> BTreeMap<std::string
 
         let rate_lv_arr = dam_obj.get("RateLv").unwrap().as_array().unwrap();
         //let mut rate_lv = Vec::<f64>::new();
-        let n0 = rate_lv_arr.get(0).unwrap().as_i64().unwrap() as f64/100.0;
+        let n0 = rate_lv_arr.first().unwrap().as_i64().unwrap() as f64/100.0;
         let n9 = rate_lv_arr.get(9).unwrap().as_i64().unwrap() as f64/100.0;
         let rate_lv = format!("[{n0}|{n9}]%");
         // for rl in rate_lv_arr {
@@ -124,26 +154,38 @@ fn parse_damage(damage: &serde_json::Map<String, Value>) -> BTreeMap<std::string
     return damage_map;
 }
 
-fn parse_skill (skill : &serde_json::Map<String, Value>) -> (SkillVariant, BTreeMap::<i64,i64>) {
-    let is_small_skill = skill.len() < 5;
+//a skill and the materials its levels cost, by item id
+struct ParsedSkill {
+    skill: SkillVariant,
+    consume: BTreeMap<i64, i64>,
+}
+
+fn parse_skill (skill : &serde_json::Map<String, Value>) -> ParsedSkill {
+    //stat bonuses have no levels; counting keys would misread a small skill that gained a field
+    let is_small_skill = !skill.contains_key("Level");
 
     let name = skill.get("Name").unwrap().as_str().unwrap().to_string();
     let mut desc = skill.get("Desc").unwrap().as_str().unwrap().to_string();
     let param = skill.get("Param").unwrap().as_array().unwrap();
-    desc = parse_skill_regex(desc, param);
+    let mut warnings = Vec::<String>::new();
+    desc = parse_skill_regex(desc, param, &mut warnings);
 
     if is_small_skill {
         let parsed_skill = SkillSmall {
             name,
-            desc
+            desc,
+            warnings
+        };
+        return ParsedSkill {
+            skill: SkillVariant::SkillS(parsed_skill),
+            consume: BTreeMap::<i64, i64>::new(),
         };
-        return (parsed_character::SkillVariant::SkillS(parsed_skill), BTreeMap::<i64, i64>::new());
     } else {
         let type_field = skill.get("Type").unwrap().as_str().unwrap().to_string();
 
         //level
         let level: &serde_json::Map<String, Value> = skill.get("Level").unwrap().as_object().unwrap();
-        let level_map = parse_level(level);
+        let level_map = parse_level(level, &mut warnings);
 
         //consume <- collect values in a map!
         let consumes: &serde_json::Map<String, Value> = skill.get("Consume").unwrap().as_object().unwrap();
@@ -158,15 +200,18 @@ fn parse_skill (skill : &serde_json::Map<String, Value>) -> (SkillVariant, BTree
             desc,
             type_field,
             level: level_map,
-            damage
+            damage,
+            warnings
+        };
+        return ParsedSkill {
+            skill: SkillVariant::SkillL(parsed_skill),
+            consume,
         };
-        return (parsed_character::SkillVariant::SkillL(parsed_skill), consume);
     }
 }
 
-pub async fn parse_character_skilltrees(skilltrees : &BTreeMap<String, serde_json::Value>) -> (BTreeMap<std::string::String, SkillTree>, std::option::Option<Value>) {
+pub fn parse_character_skilltrees(skilltrees : &BTreeMap<String, serde_json::Value>, item_map : Option<&Value>) -> BTreeMap<std::string::String, SkillTree> {
     let mut skill_map = BTreeMap::<String, SkillTree>::new();
-    let item_map = get_item_object().await;
 
     for (main_key, value) in skilltrees {
         let skilltree = value.as_object().unwrap();
@@ -188,13 +233,13 @@ pub async fn parse_character_skilltrees(skilltrees : &BTreeMap<String, serde_jso
         let unlock = skilltree.get("UnLockCondition").unwrap().as_i64().unwrap();
         //skill (variable)
         let skill: &serde_json::Map<String, Value> = skilltree.get("Skill").unwrap().as_object().unwrap();
-        let (new_skill, skill_consume_map) = parse_skill(skill);
+        let parsed_skill = parse_skill(skill);
 
-        for (key, value) in skill_consume_map {
+        for (key, value) in parsed_skill.consume {
             add_or_update_map(&mut consume_map, &key, &value);
         }
 
-        let named_map = match_item_names(&item_map, &consume_map);
+        let named_map = match_item_names(item_map, &consume_map);
 
         let new_skill_tree = SkillTree {
             parent_nodes: parent_vec,
@@ -202,39 +247,27 @@ pub async fn parse_character_skilltrees(skilltrees : &BTreeMap<String, serde_jso
             consume: named_map,
             coordinate: coord,
             un_lock_condition: unlock,
-            skill: new_skill
+            skill: parsed_skill.skill
         };
         skill_map.insert(main_key.clone(), new_skill_tree);
     }
-    return (skill_map, item_map);
+    return skill_map;
 }
 
 pub fn parse_chains (chains: BTreeMap<String, ChainDescription>) -> BTreeMap<String, ParsedChainDescription> {
     let mut parsed_chains = BTreeMap::<String, ParsedChainDescription>::new();
     for (key, chain) in chains {
-        let desc = parse_desc_regex(chain.desc, &chain.param);
+        let mut warnings = Vec::<String>::new();
+        let desc = parse_desc_regex(chain.desc, &chain.param, &mut warnings);
         parsed_chains.insert(key, ParsedChainDescription {
             name: chain.name,
-            desc
+            desc,
+            warnings
         });
     }
     return parsed_chains;
 }
 
-async fn get_item_object () -> Option<Value> {
-    let url = "https://api.hakush.in/ww/data/en/item.json";
-    if let Ok(get_url) = reqwest::Url::parse(url) {
-        if let Ok(response) = reqwest::get(get_url).await {
-            if response.status() == reqwest::StatusCode::OK {
-                if let Ok(result) = response.json::<Value>().await {
-                    return Some(result);
-                }
-            }
-        }
-    }
-    return None;
-}
-
 pub fn parse_stats (stats : N90) -> ParsedStats {
     let life = stats.life.round() as i64;
     let atk = stats.atk.round() as i64;
@@ -246,7 +279,7 @@ pub fn parse_stats (stats : N90) -> ParsedStats {
     };
 }
 
-pub fn parse_ascensions (item_map : &Option<Value>, ascensions : BTreeMap<String, Vec<Consume>>) -> BTreeMap<String, BTreeMap<String, i64>> {
+pub fn parse_ascensions (item_map : Option<&Value>, ascensions : BTreeMap<String, Vec<Consume>>) -> BTreeMap<String, BTreeMap<String, i64>> {
     let mut all_asc_map = BTreeMap::<String, BTreeMap::<String, i64>>::new();
     for (key, asc) in ascensions {
         let mut this_asc_map = BTreeMap::<String, i64>::new();
@@ -265,11 +298,12 @@ pub fn parse_ascensions (item_map : &Option<Value>, ascensions : BTreeMap<String
     return all_asc_map;
 }
 
+//an item the catalog doesn't name, including one whose "name" was renamed or retyped, keeps its id
 fn match_value (items: &Value, key : &i64) -> String {
     let key_str = key.to_string();
-    match items.get(&key_str) {
-        Some(item_value) => {
-            return item_value.get("name").unwrap().as_str().unwrap().to_string();
+    match items.get(&key_str).and_then(|item_value| item_value.get("name")).and_then(Value::as_str) {
+        Some(name) => {
+            return name.to_string();
         },
         None => {
            return key_str;
@@ -277,7 +311,7 @@ fn match_value (items: &Value, key : &i64) -> String {
     }
 }
 
-fn match_item_names (items: &Option<Value>, map : &BTreeMap<i64,i64>) -> BTreeMap<std::string::String, i64> {
+fn match_item_names (items: Option<&Value>, map : &BTreeMap<i64,i64>) -> BTreeMap<std::string::String, i64> {
     let mut named_map: BTreeMap<String, i64> = BTreeMap::<String, i64>::new();
     match items {
         Some(item_map) => {
@@ -294,15 +328,17 @@ fn match_item_names (items: &Option<Value>, map : &BTreeMap<i64,i64>) -> BTreeMa
     return named_map;
 }
 
-fn parse_desc_regex(desc: String, param: &Vec<String>) -> String {
+fn parse_desc_regex(desc: String, param: &[String], warnings: &mut Vec<String>) -> String {
     let re = Regex::new(r"\{([0-9]+)\}").unwrap();
     let haystack = &desc;
     let interpolated = re.replace_all(haystack, |caps: &Captures| {
         let Ok(index) = caps[1].parse::<usize>() else {
-            return caps[1].to_string();
+            warnings.push(format!("placeholder {{{}}} is not a valid param index", &caps[1]));
+            return caps[0].to_string();
         };
         let Some(int) = param.get(index) else {
-            return caps[1].to_string();
+            warnings.push(format!("placeholder {{{index}}} has no matching param ({} available)", param.len()));
+            return caps[0].to_string();
         };
         int.to_string()
     });
@@ -325,32 +361,85 @@ fn parse_desc_regex(desc: String, param: &Vec<String>) -> String {
     return new_desc;
 }
 
-fn parse_skill_regex(desc: String, param: &Vec<Value>) -> String {
+fn parse_skill_regex(desc: String, param: &[Value], warnings: &mut Vec<String>) -> String {
     let mut new_param = Vec::<String>::new();
     for p in param {
         new_param.push(p.as_str().unwrap().to_string());
     }
-    parse_desc_regex(desc, &new_param)
+    parse_desc_regex(desc, &new_param, warnings)
 }
 
-fn parse_level_regex (level_format: String, params: &Vec<Value>) -> String {
-    let re = Regex::new(r"\{([0-9]+)\}").unwrap();
-    let haystack = &level_format;
-    let n0 = params.get(0).unwrap().as_str().unwrap().to_string();
-    let formatted_text = match params.get(9) {
+//"[a|b]" for a list of per-level values, or just "a" when level 10 doesn't change it
+fn level_range(values: &[Value]) -> Option<String> {
+    let n0 = values.first()?.as_str().unwrap().to_string();
+    match values.get(9) {
         Some(n9_value) => {
             let n9 = n9_value.as_str().unwrap().to_string();
             if n9.eq(&n0) {
-                format!("{n0}")
+                return Some(n0);
             } else {
-                format!("[{n0}|{n9}]")
+                return Some(format!("[{n0}|{n9}]"));
             }
         },
         None => {
-            format!("{n0}")
+            return Some(n0);
         },
-    };
+    }
+}
 
-    let new_level_format = re.replace_all(haystack, formatted_text);
+//fills placeholder {N} from param list N
+fn parse_level_regex (level_format: String, param_lists: &[Value], name: &str, warnings: &mut Vec<String>) -> String {
+    let re = Regex::new(r"\{([0-9]+)\}").unwrap();
+    let haystack = &level_format;
+    let new_level_format = re.replace_all(haystack, |caps: &Captures| {
+        let values = caps[1].parse::<usize>().ok().and_then(|index| param_lists.get(index)).and_then(Value::as_array);
+        match values.and_then(|values| level_range(values)) {
+            Some(range) => range,
+            None => {
+                warnings.push(format!("level \"{name}\" placeholder {} has no matching param list ({} available)", &caps[0], param_lists.len()));
+                caps[0].to_string()
+            },
+        }
+    });
     return new_level_format.to_string();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn level_table(format: &str, param: Value) -> serde_json::Map<String, Value> {
+        let level = json!({ "1": { "Name": "Skill DMG", "Format": format, "Param": param } });
+        return level.as_object().unwrap().clone();
+    }
+
+    #[test]
+    fn single_param_list_levels_have_no_warnings() {
+        let params: Vec<String> = (1..=10).map(|level| format!("{level}%")).collect();
+        let mut warnings = Vec::<String>::new();
+        let levels = parse_level(&level_table("{0}", json!([params])), &mut warnings);
+        assert_eq!(levels["1"].format, "[1%|10%]");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn placeholders_are_filled_from_their_own_param_list() {
+        let first: Vec<String> = (1..=10).map(|level| format!("{level}%")).collect();
+        let second: Vec<String> = (1..=10).map(|level| format!("{level}s")).collect();
+        let mut warnings = Vec::<String>::new();
+        let levels = parse_level(&level_table("{0} for {1}", json!([first, second])), &mut warnings);
+        assert_eq!(levels["1"].format, "[1%|10%] for [1s|10s]");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn placeholders_without_a_param_list_are_reported() {
+        let params: Vec<String> = (1..=10).map(|level| format!("{level}%")).collect();
+        let mut warnings = Vec::<String>::new();
+        let levels = parse_level(&level_table("{0} for {1}", json!([params])), &mut warnings);
+        assert_eq!(levels["1"].format, "[1%|10%] for {1}");
+        assert_eq!(warnings, vec!["level \"Skill DMG\" placeholder {1} has no matching param list (1 available)".to_string()]);
+    }
+}
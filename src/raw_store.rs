@@ -0,0 +1,89 @@
+use std::{fs::{self, File}, io::BufReader};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{read_and_write_funcs::write_json_atomic, storage::Storage};
+
+//hashes of the raw payloads a parsed snapshot was built from
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawManifest {
+    pub id: i64,
+    pub name: String,
+    pub character: String,
+    pub items: Option<String>,
+}
+
+//store a payload under the sha256 of its contents, returning the hash
+pub fn store_raw(storage: &Storage, value: &Value) -> Option<String> {
+    let bytes = serde_json::to_vec(value).ok()?;
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    let dir = storage.raw_objects_dir();
+    fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("{hash}.json"));
+    //an existing object is only trusted if it still holds the payload, so a torn write gets replaced
+    if load_raw(storage, &hash).as_ref() != Some(value) {
+        if let Err(err) = write_json_atomic(&path, value) {
+            eprintln!("{:#?}", err);
+            return None;
+        }
+    }
+    return Some(hash);
+}
+
+pub fn load_raw(storage: &Storage, hash: &str) -> Option<Value> {
+    let file = File::open(storage.raw_objects_dir().join(format!("{hash}.json"))).ok()?;
+    return serde_json::from_reader(BufReader::new(file)).ok();
+}
+
+pub fn write_manifest(storage: &Storage, manifest: &RawManifest) {
+    if fs::create_dir_all(storage.character_dir(manifest.id)).is_err() {
+        return;
+    }
+    if let Err(err) = write_json_atomic(&storage.raw_manifest_path(manifest.id), manifest) {
+        eprintln!("{:#?}", err);
+    }
+}
+
+pub fn read_manifest(storage: &Storage, id: i64) -> Option<RawManifest> {
+    let file = File::open(storage.raw_manifest_path(id)).ok()?;
+    return serde_json::from_reader(BufReader::new(file)).ok();
+}
+
+pub fn read_manifests(storage: &Storage) -> Vec<RawManifest> {
+    return storage.stored_ids().into_iter().filter_map(|id| read_manifest(storage, id)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_storage(name: &str) -> Storage {
+        let root = std::env::temp_dir().join(format!("hakushin_wuwa_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        return Storage::new(root);
+    }
+
+    #[test]
+    fn stored_payloads_load_back() {
+        let storage = temp_storage("raw_roundtrip");
+        let payload: Value = serde_json::from_str(include_str!("fixtures/character.raw.json")).unwrap();
+        let hash = store_raw(&storage, &payload).unwrap();
+        assert_eq!(load_raw(&storage, &hash), Some(payload));
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+
+    #[test]
+    fn torn_objects_are_rewritten() {
+        let storage = temp_storage("raw_torn");
+        let payload: Value = serde_json::from_str(include_str!("fixtures/character.raw.json")).unwrap();
+        let hash = store_raw(&storage, &payload).unwrap();
+        let path = storage.raw_objects_dir().join(format!("{hash}.json"));
+        fs::write(&path, "{\"Id\": 15").unwrap();
+        assert_eq!(load_raw(&storage, &hash), None);
+        assert_eq!(store_raw(&storage, &payload), Some(hash.clone()));
+        assert_eq!(load_raw(&storage, &hash), Some(payload));
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+}
@@ -1,113 +1,261 @@
-use std::{fs::File, io::{self, BufReader, Seek, SeekFrom}};
+use std::{fs::{self, File}, io::{self, BufReader, BufWriter, Write}, path::Path};
 
-use crate::{character_list::MinimalCharacterMap, parsed_character::ParsedCharacter};
+use crate::{change_report::{build_report, ChangeReport}, character_list::{compare_rosters, MinimalCharacterMap, RosterChanges}, history::{list_versions, record_snapshot}, migration::{read_character_file, SnapshotError}, parsed_character::ParsedCharacter, storage::Storage, webhook::Notifier};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use serde_json::json;
 use serde_json_diff::Difference;
 
-pub async fn write_to_file(character : ParsedCharacter){
-    let title = format!("{}.json", character.name);
-
-    if let Ok(mut file) = File::options()
-    .read(true)
-    .write(true)
-    .create(true)
-    .open(&title) {
-        let reader = BufReader::new(&file);
-        let saved_content: Result<ParsedCharacter, serde_json::Error> = serde_json::from_reader(reader);
-        match saved_content {
-            Ok(saved_char) => {
-                let updated = compare_characters(&saved_char, &character).await;
-                if updated {
-                    write_character_to_file(&mut file, &character, &title, true);
-                }
-            },
-            Err(_) => {
-                //file didn't exist before
-                write_character_to_file(&mut file, &character, &title, false);
-            },
-        }
+//what saving a character did to its snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveOutcome {
+    Created,
+    Updated(ChangeReport),
+    Unchanged,
+}
+
+impl SaveOutcome {
+    pub fn is_changed(&self) -> bool {
+        *self != SaveOutcome::Unchanged
     }
 }
 
-pub fn get_ids_from_user() -> String {
-    let mut buffer: String = String::new();
-    println!("Enter IDs: ");
-    let stdin: io::Stdin = io::stdin();
-    match stdin.read_line(&mut buffer) {
-        Ok(_) => {
-            buffer
+//fails when nothing could be written, so a run that saved nothing doesn't look like a success
+pub async fn write_to_file(storage : &Storage, character : ParsedCharacter, notifier : &Notifier) -> Result<SaveOutcome, String> {
+    let outcome = save_snapshot(storage, &character)?;
+    storage.add_to_index(&character.name, character.id);
+    //only once the change is saved and the lock released, so a slow webhook doesn't hold up other runs
+    if let SaveOutcome::Updated(report) = &outcome {
+        notifier.notify(&character, report).await;
+    }
+    return Ok(outcome);
+}
+
+fn save_snapshot(storage : &Storage, character : &ParsedCharacter) -> Result<SaveOutcome, String> {
+    let path = storage.snapshot_path(character.id);
+    let dir = storage.character_dir(character.id);
+    fs::create_dir_all(&dir).map_err(|err| format!("Could not create {}: {err}", dir.display()))?;
+    //held until the snapshot is written, so concurrent runs take turns on the same character
+    let _lock = lock_path(&path)?;
+    let title = format!("{} ({})", character.name, path.display());
+
+    match read_saved_character(storage, character) {
+        Ok(Some((saved_char, saved_at))) => match compare_characters(storage, &saved_char, character) {
+            Some(report) => {
+                if list_versions(storage, character.id).is_empty() {
+                    //saved before history existed, so keep it as the first version
+                    record_snapshot(storage, &saved_char, saved_at)?;
+                }
+                write_character_to_file(character, &path, &title, true)?;
+                record_snapshot(storage, character, Utc::now())?;
+                return Ok(SaveOutcome::Updated(report));
+            },
+            None => return Ok(SaveOutcome::Unchanged),
         },
-        Err(_) => {
-            String::new()
+        Ok(None) => {
+            //file didn't exist before
+            write_character_to_file(character, &path, &title, false)?;
+            record_snapshot(storage, character, Utc::now())?;
+            return Ok(SaveOutcome::Created);
         },
+        //replacing a file that can't be read would lose it, whether it's damaged or from a newer version
+        Err(err) => return Err(format!("{title} was not saved, the saved snapshot could not be read: {err}")),
+    }
+}
+
+//the current snapshot and when it was saved, falling back to a pre-id-layout "<Name>.json" for the same id;
+//older formats are migrated on the way in, so they still diff against the new character
+fn read_saved_character(storage : &Storage, character : &ParsedCharacter) -> Result<Option<(ParsedCharacter, DateTime<Utc>)>, SnapshotError> {
+    let read = |path: &Path| -> Option<Result<(ParsedCharacter, DateTime<Utc>), SnapshotError>> {
+        let saved_at = fs::metadata(path).ok()?.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        Some(read_character_file(path).map(|saved| (saved, saved_at)))
+    };
+    if let Some(saved) = read(&storage.snapshot_path(character.id)) {
+        return saved.map(Some);
+    }
+    match read(&storage.legacy_snapshot_path(&character.name)) {
+        Some(Ok((saved_char, saved_at))) if saved_char.id == character.id => Ok(Some((saved_char, saved_at))),
+        _ => Ok(None),
     }
 }
 
-async fn compare_characters(old_char : &ParsedCharacter, new_char : &ParsedCharacter) -> bool {
+//takes an advisory lock on "<path>.lock", released when the returned file is dropped
+pub fn lock_path(path: &Path) -> Result<File, String> {
+    let mut lock_name = path.as_os_str().to_owned();
+    lock_name.push(".lock");
+    let lock_file = File::options().write(true).create(true).truncate(false).open(&lock_name);
+    return lock_file.and_then(|file| file.lock().map(|_| file)).map_err(|err| format!("Could not lock {}: {err}", path.display()));
+}
+
+//writes to a temporary file next to the target, then renames it over the target
+pub fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    return write_atomic(path, |writer| serde_json::to_writer_pretty(writer, value).map_err(io::Error::from));
+}
+
+pub fn write_bytes_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    return write_atomic(path, |writer| writer.write_all(bytes));
+}
+
+fn write_atomic(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let result = File::create(&temp_path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+        fs::rename(&temp_path, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    return result;
+}
+
+//a missing snapshot is told apart from one that can't be read, e.g. because a newer version saved it
+pub fn read_snapshot(storage : &Storage, id : i64) -> Result<ParsedCharacter, String> {
+    let path = storage.snapshot_path(id);
+    if !path.exists() {
+        return Err(format!("No snapshot saved for {id}."));
+    }
+    return read_character_file(&path).map_err(|err| format!("{} could not be read: {err}", path.display()));
+}
+
+pub fn read_character_list(storage : &Storage) -> Option<MinimalCharacterMap> {
+    let file = File::open(storage.character_list_path()).ok()?;
+    return serde_json::from_reader(BufReader::new(file)).ok();
+}
+
+pub fn diff_characters(old_char : &ParsedCharacter, new_char : &ParsedCharacter) -> Option<Difference> {
     let old_char_json = json!(old_char);
     let new_char_json = json!(new_char);
+    return serde_json_diff::values(old_char_json, new_char_json);
+}
 
-    match serde_json_diff::values(old_char_json, new_char_json) {
+//writes the diff and report and notifies webhooks when the character changed, returning the report
+fn compare_characters(storage : &Storage, old_char : &ParsedCharacter, new_char : &ParsedCharacter) -> Option<ChangeReport> {
+    match diff_characters(old_char, new_char) {
         Some(differences) => {
-            println!("{differences:#?}");
-            write_diff_to_file(&differences, &old_char.name);
-            true
+            let report = build_report(old_char, new_char, &differences);
+            eprint!("{}", report.to_plain_text());
+            write_diff_to_file(&differences, &storage.changes_path(new_char.id));
+            write_report_to_file(&report, &storage.report_path(new_char.id));
+            Some(report)
         },
         None => {
-            false
+            None
         },
     }
 }
 
-fn write_diff_to_file(character : &Difference, name: &String){
-    let title = format!("Changes to {}.json", name);
+fn write_diff_to_file(character : &Difference, path: &Path){
+    let title = path.display();
 
-    if let Ok(file) = File::options()
-    //.read(true)
-    .write(true)
-    .truncate(true)
-    .create(true)
-    .open(&title) {
-        //let reader = BufReader::new(&file);
-        let write_outcome = serde_json::to_writer_pretty(file, &character);
-        match write_outcome {
-            Ok(_) => {
-                println!("{title} created.");
-            },
-            Err(_) => {
-                println!("Error with {title}.");
-            },
-        }
+    match write_json_atomic(path, &character) {
+        Ok(_) => {
+            eprintln!("{title} created.");
+        },
+        Err(_) => {
+            eprintln!("Error with {title}.");
+        },
     }
 }
 
-fn write_character_to_file(file: &mut File, character: &ParsedCharacter, title: &String, update: bool){
-    let _ = file.seek(SeekFrom::Start(0));
-    match serde_json::to_writer_pretty(file, &character) {
+fn write_report_to_file(report: &ChangeReport, path: &Path){
+    let title = path.display();
+    match fs::write(path, report.to_markdown()) {
         Ok(_) => {
-            if update {
-                println!("{title} updated.");
-            } else {
-                println!("{title} created.");
-            }
+            eprintln!("{title} created.");
         },
-        Err(err) => {
-            println!("{:#?}", err);
+        Err(_) => {
+            eprintln!("Error with {title}.");
         },
     }
 }
 
-pub fn write_character_list_to_file(map: &MinimalCharacterMap){
-    let path = "characters.json";
-    let mut file = File::create(path).unwrap();
-    let _ = file.seek(SeekFrom::Start(0));
-    match serde_json::to_writer_pretty(file, &map) {
+fn write_character_to_file(character: &ParsedCharacter, path: &Path, title: &String, update: bool) -> Result<(), String> {
+    write_json_atomic(path, &character).map_err(|err| format!("Could not write {title}: {err}"))?;
+    if update {
+        eprintln!("{title} updated.");
+    } else {
+        eprintln!("{title} created.");
+    }
+    return Ok(());
+}
+
+//saves the roster, returning how it differs from the one saved before
+pub fn write_character_list_to_file(storage: &Storage, map: &MinimalCharacterMap) -> RosterChanges {
+    let path = storage.character_list_path();
+    if let Err(err) = fs::create_dir_all(&storage.root) {
+        eprintln!("{:#?}", err);
+        return RosterChanges::default();
+    }
+    //with no saved roster there is nothing to compare against, rather than everything being new
+    let changes = match read_character_list(storage) {
+        Some(saved_map) => compare_rosters(&saved_map, map),
+        None => RosterChanges::default(),
+    };
+    if !changes.is_empty() {
+        log_roster_changes(storage, &changes);
+    }
+    //status goes to stderr so `list --format json|csv` output stays machine-readable
+    match write_json_atomic(&path, &map) {
         Ok(_) => {
-            println!("{path} created.");
+            eprintln!("{} created.", path.display());
             }
         ,
         Err(err) => {
-            println!("{:#?}", err);
+            eprintln!("{:#?}", err);
         },
     }
-}
\ No newline at end of file
+    return changes;
+}
+
+//keeps a dated record of roster changes, since characters.json only holds the latest roster
+fn log_roster_changes(storage: &Storage, changes: &RosterChanges) {
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+    let mut entry = String::new();
+    for line in changes.lines() {
+        eprintln!("{line}");
+        entry.push_str(&format!("{timestamp}  {line}\n"));
+    }
+    let logged = File::options().create(true).append(true).open(storage.roster_log_path()).and_then(|mut file| file.write_all(entry.as_bytes()));
+    if let Err(err) = logged {
+        eprintln!("{:#?}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{http::HttpSettings, parsed_character::FORMAT_VERSION};
+
+    use super::*;
+
+    #[test]
+    fn shorter_rewrites_leave_no_stale_bytes() {
+        let dir = std::env::temp_dir().join(format!("hakushin_wuwa_atomic_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Shorekeeper.json");
+        write_json_atomic(&path, &json!({ "name": "Shorekeeper", "tags": ["Support and Healer", "DMG Amplification", "Traction"] })).unwrap();
+        write_json_atomic(&path, &json!({ "name": "Shorekeeper" })).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, json!({ "name": "Shorekeeper" }));
+        //only the target is left, no temporary files
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn saving_a_smaller_character_diffs_against_the_old_one() {
+        let storage = Storage::new(std::env::temp_dir().join(format!("hakushin_wuwa_smaller_{}", std::process::id())));
+        let notifier = Notifier::new(Vec::new(), false, HttpSettings::default());
+        let mut character = ParsedCharacter { format_version: FORMAT_VERSION, id: 1505, name: "Shorekeeper".to_string(), ..Default::default() };
+        character.ascensions.insert("1".to_string(), BTreeMap::from([("Shell Credit".to_string(), 5000), ("Lunarcon".to_string(), 4)]));
+        assert_eq!(write_to_file(&storage, character.clone(), &notifier).await, Ok(SaveOutcome::Created));
+        character.ascensions.clear();
+        assert!(matches!(write_to_file(&storage, character.clone(), &notifier).await, Ok(SaveOutcome::Updated(_))));
+        assert_eq!(read_snapshot(&storage, 1505), Ok(character));
+        fs::remove_dir_all(&storage.root).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::{character::Character, json_schema::{validate, IssueKind}, parsed_character::ParsedCharacter, parsing_funcs::parse_character};
+
+//what the parsers expect of a character payload; kept by hand, since most of it sits behind
+//serde_json::Value fields in Character
+pub const RAW_CHARACTER_SCHEMA: &str = include_str!("schemas/character.raw.schema.json");
+
+/// How a raw character payload differs from the format the parsers know. Paths are JSON
+/// pointers with numeric keys (skill trees, levels, chains...) collapsed to "*", each with
+/// the number of places it occurs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriftReport {
+    /// Fields the parsers don't know about, which they ignore.
+    pub new_fields: BTreeMap<String, usize>,
+    /// Fields the parsers need that the payload lacks.
+    pub missing_fields: BTreeMap<String, usize>,
+    /// Fields whose type changed, as "path: expected -> found".
+    pub type_changes: BTreeMap<String, usize>,
+    /// Anything else the parsers can't handle, such as a list that got too short.
+    pub other: BTreeMap<String, usize>,
+}
+
+impl DriftReport {
+    /// Whether the payload matches the known format exactly.
+    pub fn is_empty(&self) -> bool {
+        return self.new_fields.is_empty() && !self.is_breaking();
+    }
+
+    /// Whether the parsers would fail on the payload; new fields alone are not breaking.
+    pub fn is_breaking(&self) -> bool {
+        return !self.missing_fields.is_empty() || !self.type_changes.is_empty() || !self.other.is_empty();
+    }
+
+    /// The report as plain text, one section per kind of change.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = String::new();
+        let sections = [("New fields", &self.new_fields), ("Missing fields", &self.missing_fields), ("Type changes", &self.type_changes), ("Other changes", &self.other)];
+        for (title, entries) in sections {
+            if entries.is_empty() {
+                continue;
+            }
+            text.push_str(&format!("{title}:\n"));
+            for (entry, count) in entries {
+                match count {
+                    1 => text.push_str(&format!("  {entry}\n")),
+                    _ => text.push_str(&format!("  {entry} ({count} places)\n")),
+                }
+            }
+        }
+        return text;
+    }
+}
+
+/// Checks a raw character payload against the format the parsers know.
+pub fn check_drift(payload: &Value) -> DriftReport {
+    let schema: Value = serde_json::from_str(RAW_CHARACTER_SCHEMA).expect("the raw character schema is valid JSON");
+    let mut report = DriftReport::default();
+    for issue in validate(&schema, payload) {
+        let path = collapse_keys(&issue.path);
+        let (entries, entry) = match issue.kind {
+            IssueKind::UnknownField => (&mut report.new_fields, path),
+            IssueKind::MissingField => (&mut report.missing_fields, path),
+            IssueKind::WrongType { expected, found } => (&mut report.type_changes, format!("{path}: {expected} -> {found}")),
+            IssueKind::NotAllowed(reason) => (&mut report.other, format!("{path}: {reason}")),
+        };
+        *entries.entry(entry).or_default() += 1;
+    }
+    return report;
+}
+
+fn collapse_keys(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').map(|segment| if !segment.is_empty() && segment.parse::<i64>().is_ok() { "*" } else { segment }).collect();
+    return segments.join("/");
+}
+
+/// Parses a raw character payload after checking it for drift from the known format.
+/// New fields are reported and ignored; missing fields and type changes are returned
+/// as an error instead of being parsed, since the parsers would fail on them.
+pub fn parse_checked(label: &str, payload: Value, item_map: Option<&Value>) -> Result<ParsedCharacter, String> {
+    let report = check_drift(&payload);
+    if report.is_breaking() {
+        return Err(format!("{label} no longer matches the known Hakushin format, so it was not parsed:\n{}", report.to_plain_text().trim_end()));
+    }
+    if !report.is_empty() {
+        eprint!("{label} has fields the parser doesn't know about:\n{}", report.to_plain_text());
+    }
+    let character = serde_json::from_value::<Character>(payload).map_err(|err| format!("Unexpected data for {label}: {err}"))?;
+    return Ok(parse_character(character, item_map));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> Value {
+        return serde_json::from_str(include_str!("fixtures/character.raw.json")).unwrap();
+    }
+
+    #[test]
+    fn known_payloads_parse_without_drift() {
+        assert!(check_drift(&payload()).is_empty());
+        assert_eq!(parse_checked("Shorekeeper", payload(), None).unwrap().name, "Shorekeeper");
+    }
+
+    #[test]
+    fn short_rate_lists_are_an_error() {
+        let mut payload = payload();
+        payload["SkillTrees"]["1"]["Skill"]["Damage"]["101"]["RateLv"] = serde_json::json!([3050, 3300]);
+        assert!(check_drift(&payload).is_breaking());
+        assert!(parse_checked("Shorekeeper", payload, None).is_err());
+    }
+
+    #[test]
+    fn missing_keys_are_an_error() {
+        let mut payload = payload();
+        payload.as_object_mut().unwrap().remove("SkillTrees");
+        let report = check_drift(&payload);
+        assert_eq!(report.missing_fields.keys().collect::<Vec<_>>(), ["/SkillTrees"]);
+        assert!(parse_checked("Shorekeeper", payload, None).is_err());
+
+        let mut payload = self::payload();
+        payload["SkillTrees"]["1"]["Skill"]["Level"]["1"].as_object_mut().unwrap().remove("Param");
+        assert_eq!(check_drift(&payload).missing_fields.keys().collect::<Vec<_>>(), ["/SkillTrees/*/Skill/Level/*/Param"]);
+        assert!(parse_checked("Shorekeeper", payload, None).is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_drift_but_still_parse() {
+        let mut payload = payload();
+        payload["SkillTrees"]["2"]["Skill"]["Icon"] = Value::from("icon.png");
+        let report = check_drift(&payload);
+        assert!(!report.is_empty() && !report.is_breaking());
+        assert_eq!(report.new_fields.keys().collect::<Vec<_>>(), ["/SkillTrees/*/Skill/Icon"]);
+        assert!(parse_checked("Shorekeeper", payload, None).is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{change_report::build_report, game_terms::{element_label, weapon_label}, history::{list_versions, load_version}, material_plan::plan_materials, parsed_character::{format_at_levels, in_key_order, ParsedCharacter, SkillVariant, FORMAT_LEVELS}, read_and_write_funcs::{diff_characters, read_snapshot}, storage::Storage};
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; line-height: 1.5; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+nav a { margin-right: 1em; }
+.meta { color: #555; }
+";
+
+//writes index.html, materials.html and a page plus a history page per saved character;
+//every link is relative, so the directory can be served from anywhere
+pub fn build_site(storage: &Storage, output: &Path) -> Result<usize, String> {
+    let ids = storage.stored_ids().into_iter().filter(|id| storage.snapshot_path(*id).exists());
+    let characters = ids.map(|id| read_snapshot(storage, id)).collect::<Result<Vec<ParsedCharacter>, String>>()?;
+    if characters.is_empty() {
+        return Err("No snapshots saved.".to_string());
+    }
+    let pages_dir = output.join("characters");
+    fs::create_dir_all(&pages_dir).map_err(|err| format!("Could not create {}: {err}", pages_dir.display()))?;
+    let write = |path: &Path, text: String| fs::write(path, text).map_err(|err| format!("Could not write {}: {err}", path.display()));
+
+    write(&output.join("style.css"), STYLE.to_string())?;
+    write(&output.join("index.html"), index_page(&characters))?;
+    write(&output.join("materials.html"), materials_page(&characters))?;
+    for character in &characters {
+        write(&pages_dir.join(format!("{}.html", character.id)), character_page(character))?;
+        write(&pages_dir.join(format!("{}-history.html", character.id)), history_page(storage, character))?;
+    }
+    return Ok(characters.len() * 2 + 2);
+}
+
+//`root` is the relative path back to the site root
+fn page(title: &str, root: &str, body: &str) -> String {
+    return format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n</head>\n<body>\n<nav><a href=\"{root}index.html\">Characters</a><a href=\"{root}materials.html\">Materials</a></nav>\n{body}</body>\n</html>\n",
+        title = escape(title),
+    );
+}
+
+fn escape(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+fn index_page(characters: &[ParsedCharacter]) -> String {
+    let mut groups = BTreeMap::<String, Vec<&ParsedCharacter>>::new();
+    for character in characters {
+        groups.entry(element_label(character.element)).or_default().push(character);
+    }
+    let mut body = "<h1>Characters</h1>\n".to_string();
+    for (element, members) in groups {
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&element)));
+        for character in members {
+            body.push_str(&format!("<li><a href=\"characters/{}.html\">{}</a> <span class=\"meta\">{}★</span></li>\n", character.id, escape(&character.name), character.rarity));
+        }
+        body.push_str("</ul>\n");
+    }
+    return page("Characters", "", &body);
+}
+
+//every material, with what each character needs of it for all ascensions and skills
+fn materials_page(characters: &[ParsedCharacter]) -> String {
+    let mut materials = BTreeMap::<String, Vec<(&ParsedCharacter, i64)>>::new();
+    for character in characters {
+        for (item, count) in plan_materials(character, 0).total() {
+            materials.entry(item).or_default().push((character, count));
+        }
+    }
+    let mut body = "<h1>Materials</h1>\n".to_string();
+    for (item, users) in materials {
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&item)));
+        for (character, count) in users {
+            body.push_str(&format!("<li><a href=\"characters/{}.html\">{}</a>: {count}</li>\n", character.id, escape(&character.name)));
+        }
+        body.push_str("</ul>\n");
+    }
+    return page("Materials", "", &body);
+}
+
+fn character_page(character: &ParsedCharacter) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape(&character.name));
+    body.push_str(&format!(
+        "<p class=\"meta\">{}★ | {} | {} | <a href=\"{}-history.html\">Change history</a></p>\n",
+        character.rarity,
+        escape(&element_label(character.element)),
+        escape(&weapon_label(character.weapon)),
+        character.id,
+    ));
+
+    if !character.tags.is_empty() {
+        body.push_str("<h2>Tags</h2>\n<ul>\n");
+        for tag in &character.tags {
+            body.push_str(&format!("<li><strong>{}</strong>: {}</li>\n", escape(&tag.name), escape(&tag.desc)));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    body.push_str("<h2>Base Stats</h2>\n");
+    body.push_str(&table(&["HP", "ATK", "DEF"], vec![vec![character.stats.life.to_string(), character.stats.atk.to_string(), character.stats.def.to_string()]]));
+
+    body.push_str("<h2>Skills</h2>\n");
+    let mut bonuses = Vec::<String>::new();
+    for (_, tree) in in_key_order(&character.skills) {
+        match &tree.skill {
+            SkillVariant::SkillL(skill) => {
+                body.push_str(&format!("<h3>{}</h3>\n<p class=\"meta\">{}</p>\n<p>{}</p>\n", escape(&skill.name), escape(&skill.type_field), escape(&skill.desc)));
+                if !skill.level.is_empty() {
+                    let [first, last] = FORMAT_LEVELS;
+                    let rows = in_key_order(&skill.level).into_iter().map(|(_, level)| {
+                        let [at_first, at_last] = format_at_levels(&level.format);
+                        vec![level.name.clone(), at_first, at_last]
+                    }).collect();
+                    body.push_str(&table(&["Level", &format!("Lv {first}"), &format!("Lv {last}")], rows));
+                }
+            },
+            SkillVariant::SkillS(skill) => bonuses.push(format!("<li><strong>{}</strong>: {}</li>\n", escape(&skill.name), escape(&skill.desc))),
+            SkillVariant::None => {},
+        }
+    }
+    if !bonuses.is_empty() {
+        body.push_str(&format!("<h3>Stat Bonuses</h3>\n<ul>\n{}</ul>\n", bonuses.concat()));
+    }
+
+    if !character.chains.is_empty() {
+        body.push_str("<h2>Resonance Chain</h2>\n<ol>\n");
+        for (_, chain) in in_key_order(&character.chains) {
+            body.push_str(&format!("<li><strong>{}</strong>: {}</li>\n", escape(&chain.name), escape(&chain.desc)));
+        }
+        body.push_str("</ol>\n");
+    }
+
+    if !character.ascensions.is_empty() {
+        body.push_str("<h2>Ascension Materials</h2>\n");
+        let mut rows = Vec::<Vec<String>>::new();
+        for (stage, items) in in_key_order(&character.ascensions) {
+            for (item, count) in items {
+                rows.push(vec![stage.clone(), item.clone(), count.to_string()]);
+            }
+        }
+        body.push_str(&table(&["Stage", "Material", "Amount"], rows));
+    }
+
+    let skill_materials = plan_materials(character, 0).skills;
+    if !skill_materials.is_empty() {
+        body.push_str("<h2>Skill Materials</h2>\n");
+        body.push_str(&table(&["Material", "Amount"], skill_materials.into_iter().map(|(item, count)| vec![item, count.to_string()]).collect()));
+    }
+    return page(&character.name, "../", &body);
+}
+
+//the report between each pair of consecutive saved versions, newest first
+fn history_page(storage: &Storage, character: &ParsedCharacter) -> String {
+    let mut body = format!("<h1>{} change history</h1>\n<p><a href=\"{}.html\">Back to {}</a></p>\n", escape(&character.name), character.id, escape(&character.name));
+    let versions = list_versions(storage, character.id);
+    let loaded: Vec<(&String, ParsedCharacter)> = versions.iter().filter_map(|version| Some((version, load_version(storage, character.id, version)?))).collect();
+    let mut entries = Vec::<String>::new();
+    if let Some((version, _)) = loaded.first() {
+        entries.push(format!("<h2>{}</h2>\n<p>First saved.</p>\n", escape(version)));
+    }
+    for pair in loaded.windows(2) {
+        let ((_, old_char), (version, new_char)) = (&pair[0], &pair[1]);
+        let Some(differences) = diff_characters(old_char, new_char) else {
+            continue;
+        };
+        let report = build_report(old_char, new_char, &differences);
+        let mut entry = format!("<h2>{}</h2>\n", escape(version));
+        for (section, lines) in &report.sections {
+            entry.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape(section)));
+            for line in lines {
+                entry.push_str(&format!("<li>{}</li>\n", escape(line)));
+            }
+            entry.push_str("</ul>\n");
+        }
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        body.push_str("<p>No saved versions.</p>\n");
+    }
+    for entry in entries.into_iter().rev() {
+        body.push_str(&entry);
+    }
+    return page(&format!("{} change history", character.name), "../", &body);
+}
+
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut text = "<table>\n<tr>".to_string();
+    for header in headers {
+        text.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    text.push_str("</tr>\n");
+    for row in rows {
+        text.push_str("<tr>");
+        for value in row {
+            text.push_str(&format!("<td>{}</td>", escape(&value)));
+        }
+        text.push_str("</tr>\n");
+    }
+    text.push_str("</table>\n");
+    return text;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::{history::record_snapshot, read_and_write_funcs::write_json_atomic};
+
+    use super::*;
+
+    #[test]
+    fn site_links_every_page_of_shorekeeper() {
+        let storage = Storage::new(std::env::temp_dir().join(format!("hakushin_wuwa_site_{}", std::process::id())));
+        let character: ParsedCharacter = serde_json::from_str(include_str!("../Shorekeeper.json")).unwrap();
+        let older = ParsedCharacter { rarity: 4, ..character.clone() };
+        record_snapshot(&storage, &older, Utc::now() - Duration::days(1)).unwrap();
+        record_snapshot(&storage, &character, Utc::now()).unwrap();
+        write_json_atomic(&storage.snapshot_path(character.id), &character).unwrap();
+        let output = storage.root.join("site");
+
+        assert_eq!(build_site(&storage, &output), Ok(4));
+        let read = |page: &str| fs::read_to_string(output.join(page)).unwrap();
+        let index = read("index.html");
+        assert!(index.contains("<h2>Spectro</h2>") && index.contains("<a href=\"characters/1505.html\">Shorekeeper</a>"), "{index}");
+        let page = read("characters/1505.html");
+        assert!(page.contains("Stage 1 DMG") && page.contains("31.78%"));
+        assert!(page.contains("href=\"../style.css\""));
+        let history = read("characters/1505-history.html");
+        assert!(history.contains("First saved.") && history.contains("Rarity"), "{history}");
+        assert!(read("materials.html").contains("Topological Confinement"));
+        fs::remove_dir_all(&storage.root).unwrap();
+    }
+}
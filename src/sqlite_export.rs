@@ -0,0 +1,254 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use rusqlite::{params, Connection, Transaction};
+use serde_json::Value;
+
+use crate::{game_terms::{element_name, weapon_name}, parsed_character::{format_at_levels, in_key_order, ParsedCharacter, SkillVariant, FORMAT_LEVELS}};
+
+//one table per model in ParsedCharacter; materials are stored by name in snapshots, so
+//material_costs.item_id is only filled in when the name is found in the item catalog
+const SCHEMA: &str = "
+CREATE TABLE characters (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    rarity INTEGER NOT NULL,
+    element INTEGER NOT NULL,
+    element_name TEXT,
+    weapon INTEGER NOT NULL,
+    weapon_name TEXT,
+    hp INTEGER NOT NULL,
+    atk INTEGER NOT NULL,
+    def INTEGER NOT NULL
+);
+CREATE TABLE tags (
+    id INTEGER PRIMARY KEY,
+    character_id INTEGER NOT NULL REFERENCES characters(id),
+    name TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+CREATE TABLE skills (
+    id INTEGER PRIMARY KEY,
+    character_id INTEGER NOT NULL REFERENCES characters(id),
+    node TEXT NOT NULL,
+    node_type INTEGER NOT NULL,
+    coordinate INTEGER NOT NULL,
+    unlock_condition INTEGER NOT NULL,
+    name TEXT,
+    type TEXT,
+    description TEXT
+);
+CREATE TABLE skill_parent_nodes (
+    skill_id INTEGER NOT NULL REFERENCES skills(id),
+    parent_node INTEGER NOT NULL
+);
+CREATE TABLE skill_levels (
+    id INTEGER PRIMARY KEY,
+    skill_id INTEGER NOT NULL REFERENCES skills(id),
+    key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    format TEXT NOT NULL
+);
+CREATE TABLE skill_level_values (
+    skill_level_id INTEGER NOT NULL REFERENCES skill_levels(id),
+    skill_level INTEGER NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE damage (
+    id INTEGER PRIMARY KEY,
+    skill_id INTEGER NOT NULL REFERENCES skills(id),
+    key TEXT NOT NULL,
+    element INTEGER NOT NULL,
+    element_power INTEGER NOT NULL,
+    energy INTEGER NOT NULL,
+    hardness_lv INTEGER NOT NULL,
+    tough_lv INTEGER NOT NULL,
+    type INTEGER NOT NULL,
+    related_property TEXT NOT NULL,
+    rate_lv TEXT NOT NULL
+);
+CREATE TABLE damage_multipliers (
+    damage_id INTEGER NOT NULL REFERENCES damage(id),
+    skill_level INTEGER NOT NULL,
+    multiplier TEXT NOT NULL
+);
+CREATE TABLE chains (
+    id INTEGER PRIMARY KEY,
+    character_id INTEGER NOT NULL REFERENCES characters(id),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+CREATE TABLE items (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+CREATE TABLE material_costs (
+    id INTEGER PRIMARY KEY,
+    character_id INTEGER NOT NULL REFERENCES characters(id),
+    ascension_stage INTEGER,
+    skill_id INTEGER REFERENCES skills(id),
+    item_id INTEGER REFERENCES items(id),
+    item_name TEXT NOT NULL,
+    count INTEGER NOT NULL
+);
+";
+
+//writes a fresh database, replacing `path` only once everything is written
+pub fn export_sqlite(path: &Path, characters: &[ParsedCharacter], items: Option<&Value>) -> Result<(), String> {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let _ = fs::remove_file(&temp_path);
+    let result = write_database(&temp_path, characters, items).and_then(|_| fs::rename(&temp_path, path).map_err(|err| err.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    return result.map_err(|err| format!("Could not write {}: {err}", path.display()));
+}
+
+fn write_database(path: &Path, characters: &[ParsedCharacter], items: Option<&Value>) -> Result<(), String> {
+    let mut connection = Connection::open(path).map_err(|err| err.to_string())?;
+    connection.execute_batch("PRAGMA foreign_keys = ON;").map_err(|err| err.to_string())?;
+    connection.execute_batch(SCHEMA).map_err(|err| err.to_string())?;
+    let transaction = connection.transaction().map_err(|err| err.to_string())?;
+    let item_ids = insert_items(&transaction, items).map_err(|err| err.to_string())?;
+    for character in characters {
+        insert_character(&transaction, character, &item_ids).map_err(|err| format!("{}: {err}", character.name))?;
+    }
+    return transaction.commit().map_err(|err| err.to_string());
+}
+
+//item name -> id, for linking material costs
+fn insert_items(transaction: &Transaction, items: Option<&Value>) -> rusqlite::Result<BTreeMap<String, i64>> {
+    let mut item_ids = BTreeMap::<String, i64>::new();
+    let Some(items) = items.and_then(Value::as_object) else {
+        return Ok(item_ids);
+    };
+    let mut insert = transaction.prepare("INSERT INTO items (id, name) VALUES (?1, ?2)")?;
+    for (id, item) in items {
+        let (Ok(id), Some(name)) = (id.parse::<i64>(), item.get("name").and_then(Value::as_str)) else {
+            continue;
+        };
+        insert.execute(params![id, name])?;
+        item_ids.entry(name.to_string()).or_insert(id);
+    }
+    return Ok(item_ids);
+}
+
+fn insert_character(transaction: &Transaction, character: &ParsedCharacter, item_ids: &BTreeMap<String, i64>) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO characters (id, name, rarity, element, element_name, weapon, weapon_name, hp, atk, def) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            character.id,
+            character.name,
+            character.rarity,
+            character.element,
+            element_name(character.element),
+            character.weapon,
+            weapon_name(character.weapon),
+            character.stats.life,
+            character.stats.atk,
+            character.stats.def,
+        ],
+    )?;
+    for tag in &character.tags {
+        transaction.execute("INSERT INTO tags (character_id, name, description) VALUES (?1, ?2, ?3)", params![character.id, tag.name, tag.desc])?;
+    }
+    let item_id = |name: &String| item_ids.get(name).copied();
+
+    for (node, tree) in in_key_order(&character.skills) {
+        let (name, type_field, desc) = match &tree.skill {
+            SkillVariant::SkillL(skill) => (Some(&skill.name), Some(&skill.type_field), Some(&skill.desc)),
+            SkillVariant::SkillS(skill) => (Some(&skill.name), None, Some(&skill.desc)),
+            SkillVariant::None => (None, None, None),
+        };
+        transaction.execute(
+            "INSERT INTO skills (character_id, node, node_type, coordinate, unlock_condition, name, type, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![character.id, node, tree.node_type, tree.coordinate, tree.un_lock_condition, name, type_field, desc],
+        )?;
+        let skill_id = transaction.last_insert_rowid();
+        for parent in &tree.parent_nodes {
+            transaction.execute("INSERT INTO skill_parent_nodes (skill_id, parent_node) VALUES (?1, ?2)", params![skill_id, parent])?;
+        }
+        for (item, count) in &tree.consume {
+            transaction.execute(
+                "INSERT INTO material_costs (character_id, skill_id, item_id, item_name, count) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![character.id, skill_id, item_id(item), item, count],
+            )?;
+        }
+        let SkillVariant::SkillL(skill) = &tree.skill else {
+            continue;
+        };
+        for (key, level) in in_key_order(&skill.level) {
+            transaction.execute(
+                "INSERT INTO skill_levels (skill_id, key, name, format) VALUES (?1, ?2, ?3, ?4)",
+                params![skill_id, key, level.name, level.format],
+            )?;
+            let level_id = transaction.last_insert_rowid();
+            for (skill_level, value) in FORMAT_LEVELS.iter().zip(format_at_levels(&level.format)) {
+                transaction.execute("INSERT INTO skill_level_values (skill_level_id, skill_level, value) VALUES (?1, ?2, ?3)", params![level_id, skill_level, value])?;
+            }
+        }
+        for (key, damage) in in_key_order(&skill.damage) {
+            transaction.execute(
+                "INSERT INTO damage (skill_id, key, element, element_power, energy, hardness_lv, tough_lv, type, related_property, rate_lv) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![skill_id, key, damage.element, damage.element_power, damage.energy, damage.hardness_lv, damage.tough_lv, damage.type_field, damage.related_property, damage.rate_lv],
+            )?;
+            let damage_id = transaction.last_insert_rowid();
+            for (skill_level, multiplier) in FORMAT_LEVELS.iter().zip(format_at_levels(&damage.rate_lv)) {
+                transaction.execute("INSERT INTO damage_multipliers (damage_id, skill_level, multiplier) VALUES (?1, ?2, ?3)", params![damage_id, skill_level, multiplier])?;
+            }
+        }
+    }
+
+    for (position, (_, chain)) in in_key_order(&character.chains).into_iter().enumerate() {
+        transaction.execute(
+            "INSERT INTO chains (character_id, position, name, description) VALUES (?1, ?2, ?3, ?4)",
+            params![character.id, position as i64 + 1, chain.name, chain.desc],
+        )?;
+    }
+    for (stage, items) in in_key_order(&character.ascensions) {
+        for (item, count) in items {
+            transaction.execute(
+                "INSERT INTO material_costs (character_id, ascension_stage, item_id, item_name, count) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![character.id, stage.parse::<i64>().ok(), item_id(item), item, count],
+            )?;
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shorekeeper_exports_to_linked_tables() {
+        let dir = std::env::temp_dir().join(format!("hakushin_wuwa_sqlite_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wuwa.sqlite");
+        let character: ParsedCharacter = serde_json::from_str(include_str!("../Shorekeeper.json")).unwrap();
+        let items: Value = serde_json::from_str(include_str!("fixtures/items.raw.json")).unwrap();
+        export_sqlite(&path, std::slice::from_ref(&character), Some(&items)).unwrap();
+        //only the database is left, no temporary file
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let connection = Connection::open(&path).unwrap();
+        let count = |sql: &str| connection.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM characters WHERE element_name = 'Spectro' AND weapon_name = 'Rectifier'"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM chains"), character.chains.len() as i64);
+        assert_eq!(count("SELECT COUNT(*) FROM skills"), character.skills.len() as i64);
+        //materials found in the catalog are linked to it, the rest only have a name
+        assert_eq!(count("SELECT SUM(count) FROM material_costs JOIN items ON items.id = item_id WHERE items.name = 'Topological Confinement'"), 46);
+        assert_eq!(count("SELECT COUNT(*) FROM material_costs WHERE item_name = 'Nova' AND item_id IS NOT NULL"), 0);
+        let stage_1: String = connection.query_row(
+            "SELECT value FROM skill_level_values JOIN skill_levels ON skill_levels.id = skill_level_id JOIN skills ON skills.id = skill_id
+             WHERE skills.name = 'Origin Calculus' AND skill_levels.name = 'Stage 1 DMG' AND skill_level = 10",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(stage_1, "31.78%");
+        assert_eq!(count("SELECT COUNT(*) FROM pragma_foreign_key_check"), 0);
+        drop(connection);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, fs::{self, File}, io::BufReader, path::PathBuf};
+
+use crate::read_and_write_funcs::{lock_path, write_json_atomic};
+
+/// Display name -> every character id using it (several Rovers share one name).
+pub type NameIndex = BTreeMap<String, Vec<i64>>;
+
+/// Where everything is saved, laid out by character id:
+///
+/// ```text
+/// <root>/characters.json, roster_changes.log
+/// <root>/index.json
+/// <root>/raw/<hash>.json
+/// <root>/cache/<hash of url>.body, .meta.json
+/// <root>/characters/<id>/snapshot.json, changes.json, changes.md, raw.json, history/
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storage {
+    /// The output directory everything else is saved under.
+    pub root: PathBuf,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage::new(".")
+    }
+}
+
+impl Storage {
+    /// Storage rooted at `root`, which is created when something is first saved.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Storage { root: root.into() }
+    }
+
+    /// The roster saved by the last `list`.
+    pub fn character_list_path(&self) -> PathBuf {
+        self.root.join("characters.json")
+    }
+
+    /// A dated log of every roster change `list` has seen.
+    pub fn roster_log_path(&self) -> PathBuf {
+        self.root.join("roster_changes.log")
+    }
+
+    /// The [`NameIndex`] of saved characters.
+    pub fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    /// Raw API payloads, each saved under the sha256 of its contents.
+    pub fn raw_objects_dir(&self) -> PathBuf {
+        self.root.join("raw")
+    }
+
+    /// The HTTP response cache, see [`ResponseCache`](crate::ResponseCache).
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    /// The directory holding one directory per saved character.
+    pub fn characters_dir(&self) -> PathBuf {
+        self.root.join("characters")
+    }
+
+    /// Everything saved for the character `id`.
+    pub fn character_dir(&self, id: i64) -> PathBuf {
+        self.characters_dir().join(id.to_string())
+    }
+
+    /// The character's current [`ParsedCharacter`](crate::ParsedCharacter) snapshot.
+    pub fn snapshot_path(&self, id: i64) -> PathBuf {
+        self.character_dir(id).join("snapshot.json")
+    }
+
+    /// The raw JSON diff of the character's last change.
+    pub fn changes_path(&self, id: i64) -> PathBuf {
+        self.character_dir(id).join("changes.json")
+    }
+
+    /// The Markdown [`ChangeReport`](crate::ChangeReport) of the character's last change.
+    pub fn report_path(&self, id: i64) -> PathBuf {
+        self.character_dir(id).join("changes.md")
+    }
+
+    /// Which raw payloads the current snapshot was parsed from.
+    pub fn raw_manifest_path(&self, id: i64) -> PathBuf {
+        self.character_dir(id).join("raw.json")
+    }
+
+    /// Every earlier snapshot of the character, one file per version.
+    pub fn history_dir(&self, id: i64) -> PathBuf {
+        self.character_dir(id).join("history")
+    }
+
+    /// Where a snapshot was saved before the id layout, as `<Name>.json` in the root.
+    pub fn legacy_snapshot_path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{name}.json"))
+    }
+
+    /// Ids of every character with a directory, in ascending order.
+    pub fn stored_ids(&self) -> Vec<i64> {
+        let mut ids = Vec::<i64>::new();
+        if let Ok(entries) = fs::read_dir(self.characters_dir()) {
+            for entry in entries.flatten() {
+                if let Some(id) = entry.file_name().to_str().and_then(|name| name.parse::<i64>().ok()) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+        return ids;
+    }
+
+    /// The saved name index, empty when there is none yet.
+    pub fn read_index(&self) -> NameIndex {
+        let Ok(file) = File::open(self.index_path()) else {
+            return NameIndex::new();
+        };
+        return serde_json::from_reader(BufReader::new(file)).unwrap_or_default();
+    }
+
+    /// Lists the character `id` under `name`, and only there, in the saved name index.
+    pub fn add_to_index(&self, name: &str, id: i64) {
+        //held until the index is written back, so concurrent runs don't drop each other's entries
+        let _lock = match lock_path(&self.index_path()) {
+            Ok(lock) => lock,
+            Err(err) => {
+                eprintln!("{err}");
+                return;
+            },
+        };
+        let mut index = self.read_index();
+        //a renamed character should only be listed under its current name
+        for ids in index.values_mut() {
+            ids.retain(|existing| *existing != id);
+        }
+        index.retain(|_, ids| !ids.is_empty());
+        let ids = index.entry(name.to_string()).or_default();
+        ids.push(id);
+        ids.sort();
+        if let Err(err) = write_json_atomic(&self.index_path(), &index) {
+            eprintln!("{:#?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_index_updates_keep_every_entry() {
+        let storage = Storage::new(std::env::temp_dir().join(format!("hakushin_wuwa_index_{}", std::process::id())));
+        let _ = fs::remove_dir_all(&storage.root);
+        fs::create_dir_all(&storage.root).unwrap();
+        let writers: Vec<_> = (0..8).map(|writer| {
+            let storage = storage.clone();
+            thread::spawn(move || {
+                for id in 0..10 {
+                    storage.add_to_index(&format!("Character {writer}"), writer * 100 + id);
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let index = storage.read_index();
+        assert_eq!(index.len(), 8);
+        assert!(index.values().all(|ids| ids.len() == 10));
+        let _ = fs::remove_dir_all(&storage.root);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::parsed_character::{format_at_levels, in_key_order, ParsedCharacter, SkillVariant, FORMAT_LEVELS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Delimiter::Comma => "csv",
+            Delimiter::Tab => "tsv",
+        }
+    }
+
+    fn separator(&self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+//one row per character, skill, Level row or Damage entry, and skill level
+pub const MULTIPLIER_HEADER: [&str; 9] = ["character_id", "character", "skill_node", "skill", "skill_type", "source", "row", "skill_level", "value"];
+//one row per character, ascension stage and item
+pub const MATERIAL_HEADER: [&str; 5] = ["character_id", "character", "stage", "item", "count"];
+
+pub fn multiplier_rows(character: &ParsedCharacter) -> Vec<Vec<String>> {
+    let mut rows = Vec::<Vec<String>>::new();
+    for (node, tree) in in_key_order(&character.skills) {
+        let SkillVariant::SkillL(skill) = &tree.skill else {
+            continue;
+        };
+        let mut push = |source: &str, row: &str, format: &str| {
+            for (skill_level, value) in FORMAT_LEVELS.iter().zip(format_at_levels(format)) {
+                rows.push(vec![
+                    character.id.to_string(),
+                    character.name.clone(),
+                    node.clone(),
+                    skill.name.clone(),
+                    skill.type_field.clone(),
+                    source.to_string(),
+                    row.to_string(),
+                    skill_level.to_string(),
+                    value,
+                ]);
+            }
+        };
+        for (_, level) in in_key_order(&skill.level) {
+            push("level", &level.name, &level.format);
+        }
+        //damage entries have no name, so they're identified by their key
+        for (key, damage) in in_key_order(&skill.damage) {
+            push("damage", key, &damage.rate_lv);
+        }
+    }
+    return rows;
+}
+
+pub fn material_rows(character: &ParsedCharacter) -> Vec<Vec<String>> {
+    let mut rows = Vec::<Vec<String>>::new();
+    for (stage, items) in in_key_order(&character.ascensions) {
+        for (item, count) in items {
+            rows.push(vec![character.id.to_string(), character.name.clone(), stage.clone(), item.clone(), count.to_string()]);
+        }
+    }
+    return rows;
+}
+
+pub fn to_delimited(header: &[&str], rows: &[Vec<String>], delimiter: Delimiter) -> String {
+    let separator = delimiter.separator().to_string();
+    let mut text = header.join(&separator);
+    text.push('\n');
+    for row in rows {
+        text.push_str(&row.iter().map(|value| field(value, delimiter)).collect::<Vec<String>>().join(&separator));
+        text.push('\n');
+    }
+    return text;
+}
+
+//csv quotes fields that need it; tsv has no quoting, so tabs and newlines become spaces
+pub fn field(text: &str, delimiter: Delimiter) -> String {
+    match delimiter {
+        Delimiter::Comma if text.contains([',', '"', '\n']) => format!("\"{}\"", text.replace('"', "\"\"")),
+        Delimiter::Comma => text.to_string(),
+        Delimiter::Tab => text.replace(['\t', '\n', '\r'], " "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shorekeeper() -> ParsedCharacter {
+        return serde_json::from_str(include_str!("../Shorekeeper.json")).unwrap();
+    }
+
+    #[test]
+    fn shorekeeper_exports_one_row_per_value() {
+        let character = shorekeeper();
+        let multipliers = multiplier_rows(&character);
+        assert!(multipliers.iter().all(|row| row.len() == MULTIPLIER_HEADER.len()));
+        let stage_1: Vec<&[String]> = multipliers.iter().filter(|row| row[3] == "Origin Calculus" && row[6] == "Stage 1 DMG").map(|row| &row[7..]).collect();
+        assert_eq!(stage_1, [["1".to_string(), "15.99%".to_string()], ["10".to_string(), "31.78%".to_string()]]);
+
+        let materials = material_rows(&character);
+        assert_eq!(materials.len(), character.ascensions.values().map(|items| items.len()).sum::<usize>());
+        assert_eq!(materials[0], ["1505", "Shorekeeper", "1", "LF Whisperin Core", "4"]);
+        let csv = to_delimited(&MATERIAL_HEADER, &materials, Delimiter::Comma);
+        assert_eq!(csv.lines().next(), Some("character_id,character,stage,item,count"));
+        assert_eq!(csv.lines().count(), materials.len() + 1);
+    }
+
+    #[test]
+    fn fields_are_escaped_for_their_delimiter() {
+        assert_eq!(field("Stage 1, Stage 2", Delimiter::Comma), "\"Stage 1, Stage 2\"");
+        assert_eq!(field("the \"Core\"", Delimiter::Comma), "\"the \"\"Core\"\"\"");
+        assert_eq!(field("a\tb\nc", Delimiter::Tab), "a b c");
+    }
+}
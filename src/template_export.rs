@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use minijinja::Environment;
+use serde::Serialize;
+
+use crate::{change_report::ChangeReport, game_terms::{element_label, weapon_label}, parsed_character::{format_at_levels, in_key_order, ParsedCharacter, ParsedTag, SkillVariant}};
+
+//name, output extension and source of the templates that ship with the tool
+pub const BUILT_IN_TEMPLATES: [(&str, &str, &str); 3] = [
+    ("discord", "md", include_str!("templates/discord.md.j2")),
+    ("bbcode", "txt", include_str!("templates/bbcode.txt.j2")),
+    ("docs", "md", include_str!("templates/docs.md.j2")),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub extension: String,
+    pub source: String,
+}
+
+impl Template {
+    //a built-in name, or a path to a minijinja (Jinja2 syntax) template file; "post.txt.j2" exports as .txt
+    pub fn load(name_or_path: &str) -> Result<Self, String> {
+        if let Some((name, extension, source)) = BUILT_IN_TEMPLATES.iter().find(|(name, _, _)| *name == name_or_path) {
+            return Ok(Template { name: name.to_string(), extension: extension.to_string(), source: source.to_string() });
+        }
+        let path = Path::new(name_or_path);
+        let source = fs::read_to_string(path).map_err(|err| {
+            let built_in: Vec<&str> = BUILT_IN_TEMPLATES.iter().map(|(name, _, _)| *name).collect();
+            format!("Could not read template {name_or_path} ({err}); the built-in templates are {}.", built_in.join(", "))
+        })?;
+        let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let stem = file_name.trim_end_matches(".j2").trim_end_matches(".jinja");
+        let extension = match stem.rsplit_once('.') {
+            Some((_, extension)) => extension.to_string(),
+            None => "txt".to_string(),
+        };
+        return Ok(Template { name: file_name, extension, source });
+    }
+
+    pub fn render(&self, character: &ParsedCharacter, changes: Option<&ChangeReport>) -> Result<String, String> {
+        let mut environment = Environment::new();
+        environment.add_template(&self.name, &self.source).map_err(|err| format!("Template {} is invalid: {err}", self.name))?;
+        let template = environment.get_template(&self.name).map_err(|err| err.to_string())?;
+        return template.render(TemplateContext::new(character, changes)).map_err(|err| format!("Rendering {} failed: {err}", self.name));
+    }
+}
+
+//what templates can use: the snapshot as `character`, plus ordered, flattened copies of its parts that are easier to loop over
+#[derive(Debug, Serialize)]
+struct TemplateContext<'a> {
+    character: &'a ParsedCharacter,
+    id: i64,
+    name: &'a str,
+    rarity: i64,
+    element: String,
+    weapon: String,
+    tags: &'a [ParsedTag],
+    stats: Stats,
+    skills: Vec<SkillContext>,
+    chains: Vec<ChainContext>,
+    ascensions: Vec<AscensionContext>,
+    //sections of the latest change report, when one was asked for
+    changes: Option<Vec<SectionContext>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    hp: i64,
+    atk: i64,
+    def: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SkillContext {
+    node: String,
+    //"large" for skills with levels, "small" for stat bonuses
+    kind: &'static str,
+    name: String,
+    #[serde(rename = "type")]
+    type_field: Option<String>,
+    desc: String,
+    levels: Vec<LevelContext>,
+    materials: Vec<ItemCount>,
+}
+
+#[derive(Debug, Serialize)]
+struct LevelContext {
+    name: String,
+    format: String,
+    lv1: String,
+    lv10: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChainContext {
+    number: usize,
+    name: String,
+    desc: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AscensionContext {
+    stage: String,
+    items: Vec<ItemCount>,
+}
+
+#[derive(Debug, Serialize)]
+struct ItemCount {
+    name: String,
+    count: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SectionContext {
+    section: String,
+    changes: Vec<String>,
+}
+
+impl<'a> TemplateContext<'a> {
+    fn new(character: &'a ParsedCharacter, changes: Option<&ChangeReport>) -> Self {
+        let items = |map: &BTreeMap<String, i64>| map.iter().map(|(name, count)| ItemCount { name: name.clone(), count: *count }).collect();
+        let skills = in_key_order(&character.skills).into_iter().filter_map(|(node, tree)| match &tree.skill {
+            SkillVariant::SkillL(skill) => Some(SkillContext {
+                node: node.clone(),
+                kind: "large",
+                name: skill.name.clone(),
+                type_field: Some(skill.type_field.clone()),
+                desc: skill.desc.clone(),
+                levels: in_key_order(&skill.level).into_iter().map(|(_, level)| {
+                    let [lv1, lv10] = format_at_levels(&level.format);
+                    LevelContext { name: level.name.clone(), format: level.format.clone(), lv1, lv10 }
+                }).collect(),
+                materials: items(&tree.consume),
+            }),
+            SkillVariant::SkillS(skill) => Some(SkillContext {
+                node: node.clone(),
+                kind: "small",
+                name: skill.name.clone(),
+                type_field: None,
+                desc: skill.desc.clone(),
+                levels: Vec::new(),
+                materials: items(&tree.consume),
+            }),
+            SkillVariant::None => None,
+        }).collect();
+
+        return TemplateContext {
+            character,
+            id: character.id,
+            name: &character.name,
+            rarity: character.rarity,
+            element: element_label(character.element),
+            weapon: weapon_label(character.weapon),
+            tags: &character.tags,
+            stats: Stats { hp: character.stats.life, atk: character.stats.atk, def: character.stats.def },
+            skills,
+            chains: in_key_order(&character.chains).into_iter().enumerate().map(|(position, (_, chain))| ChainContext {
+                number: position + 1,
+                name: chain.name.clone(),
+                desc: chain.desc.clone(),
+            }).collect(),
+            ascensions: in_key_order(&character.ascensions).into_iter().map(|(stage, stage_items)| AscensionContext {
+                stage: stage.clone(),
+                items: items(stage_items),
+            }).collect(),
+            changes: changes.map(|report| report.sections.iter().map(|(section, lines)| SectionContext {
+                section: section.clone(),
+                changes: lines.clone(),
+            }).collect()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shorekeeper() -> ParsedCharacter {
+        return serde_json::from_str(include_str!("../Shorekeeper.json")).unwrap();
+    }
+
+    #[test]
+    fn built_in_templates_render_shorekeeper() {
+        let character = shorekeeper();
+        let report = ChangeReport { character: character.name.clone(), sections: vec![("Stats".to_string(), vec!["HP: 16000 → 16713".to_string()])] };
+        for (name, _, _) in BUILT_IN_TEMPLATES {
+            let template = Template::load(name).unwrap();
+            let text = template.render(&character, None).unwrap();
+            assert!(text.contains("Shorekeeper"), "{name}: {text}");
+            assert!(text.contains("Stage 1 DMG") && text.contains("31.78%"), "{name} is missing the skill levels");
+            assert!(!text.contains("16000"), "{name} has changes without a report");
+            assert!(template.render(&character, Some(&report)).unwrap().contains("HP: 16000 → 16713"), "{name} is missing the changes");
+        }
+    }
+
+    #[test]
+    fn custom_templates_take_their_extension_from_the_file_name() {
+        let dir = std::env::temp_dir().join(format!("hakushin_wuwa_template_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("summary.csv.j2");
+        fs::write(&path, "{{ name }},{{ element }},{{ weapon }},{{ ascensions | length }}").unwrap();
+        let template = Template::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(template.extension, "csv");
+        assert_eq!(template.render(&shorekeeper(), None), Ok("Shorekeeper,Spectro,Rectifier,6".to_string()));
+
+        fs::write(&path, "{% for skill in skills %}").unwrap();
+        let err = Template::load(path.to_str().unwrap()).unwrap().render(&shorekeeper(), None).unwrap_err();
+        assert!(err.starts_with("Template summary.csv.j2 is invalid"), "{err}");
+        assert!(Template::load("missing").unwrap_err().contains("discord, bbcode, docs"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+//a request the stand-in received, with its request line and headers in `head`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub head: String,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<String> {
+        let prefix = format!("{}:", name.to_lowercase());
+        return self.head.lines().find_map(|line| line.to_lowercase().starts_with(&prefix).then(|| line[prefix.len()..].trim().to_string()));
+    }
+}
+
+//a canned answer: the status with any extra header lines, e.g. "503 Service Unavailable\r\nRetry-After: 0", and a body
+pub type Response = (&'static str, &'static str);
+
+//a local HTTP server answering requests with `responses` in turn, repeating the last one, and keeping every request;
+//returns its base url
+pub async fn stand_in(responses: Vec<Response>, requests: Arc<Mutex<Vec<Request>>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        for answered in 0.. {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut received = Vec::<u8>::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                received.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&received).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let request = Request { head: head.to_string(), body: body.to_string() };
+                    let length = request.header("content-length").map(|value| value.parse::<usize>().unwrap()).unwrap_or(0);
+                    if body.len() >= length {
+                        requests.lock().unwrap().push(request);
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            let (status, body) = responses[answered.min(responses.len() - 1)];
+            let _ = stream.write_all(format!("HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).as_bytes()).await;
+        }
+    });
+    return format!("http://{address}");
+}
@@ -0,0 +1,160 @@
+use std::{fs::File, io::Write, path::PathBuf, process::{Command, Stdio}};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{character_list::RosterChanges, read_and_write_funcs::SaveOutcome};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RosterEntry {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RosterRename {
+    pub id: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportSection {
+    pub section: String,
+    pub changes: Vec<String>,
+}
+
+//something `watch` noticed, written as one json object per line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    RosterChanged {
+        added: Vec<RosterEntry>,
+        removed: Vec<RosterEntry>,
+        renamed: Vec<RosterRename>,
+    },
+    //hashes of the stored raw item catalogs
+    ItemsChanged {
+        old_hash: String,
+        new_hash: String,
+    },
+    CharacterAdded {
+        id: i64,
+        name: String,
+    },
+    CharacterChanged {
+        id: i64,
+        name: String,
+        changes: Vec<ReportSection>,
+    },
+    //a failed fetch doesn't stop watching, it's reported and retried next poll
+    Error {
+        message: String,
+    },
+}
+
+impl WatchEvent {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, WatchEvent::Error { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimedEvent {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WatchEvent,
+}
+
+pub fn roster_event(changes: &RosterChanges) -> Option<WatchEvent> {
+    if changes.is_empty() {
+        return None;
+    }
+    let entries = |pairs: &[(String, String)]| pairs.iter().map(|(id, name)| RosterEntry { id: id.clone(), name: name.clone() }).collect();
+    return Some(WatchEvent::RosterChanged {
+        added: entries(&changes.added),
+        removed: entries(&changes.removed),
+        renamed: changes.renamed.iter().map(|(id, old_name, new_name)| RosterRename {
+            id: id.clone(),
+            old_name: old_name.clone(),
+            new_name: new_name.clone(),
+        }).collect(),
+    });
+}
+
+pub fn character_event(id: i64, name: &str, outcome: &SaveOutcome) -> Option<WatchEvent> {
+    match outcome {
+        SaveOutcome::Created => Some(WatchEvent::CharacterAdded { id, name: name.to_string() }),
+        SaveOutcome::Updated(report) => Some(WatchEvent::CharacterChanged {
+            id,
+            name: name.to_string(),
+            changes: report.sections.iter().map(|(section, changes)| ReportSection {
+                section: section.clone(),
+                changes: changes.clone(),
+            }).collect(),
+        }),
+        SaveOutcome::Unchanged => None,
+    }
+}
+
+//somewhere events are delivered to, besides stdout
+pub trait EventSink {
+    fn emit(&mut self, event: &TimedEvent) -> Result<(), String>;
+}
+
+pub struct StdoutSink;
+
+impl EventSink for StdoutSink {
+    fn emit(&mut self, event: &TimedEvent) -> Result<(), String> {
+        let line = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        let mut stdout = std::io::stdout().lock();
+        return writeln!(stdout, "{line}").and_then(|_| stdout.flush()).map_err(|err| err.to_string());
+    }
+}
+
+//appends every event to a json lines file
+pub struct FileSink {
+    pub path: PathBuf,
+}
+
+impl EventSink for FileSink {
+    fn emit(&mut self, event: &TimedEvent) -> Result<(), String> {
+        let line = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        let written = File::options().create(true).append(true).open(&self.path).and_then(|mut file| writeln!(file, "{line}"));
+        return written.map_err(|err| format!("Could not write event to {}: {err}", self.path.display()));
+    }
+}
+
+//runs a shell command for every event, with the event's json on its stdin
+pub struct CommandSink {
+    pub command: String,
+}
+
+impl EventSink for CommandSink {
+    fn emit(&mut self, event: &TimedEvent) -> Result<(), String> {
+        let line = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        let mut child = Command::new("sh").arg("-c").arg(&self.command).stdin(Stdio::piped()).spawn()
+            .map_err(|err| format!("Could not run {}: {err}", self.command))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            //the command may not read its input, which isn't an error
+            let _ = writeln!(stdin, "{line}");
+        }
+        let status = child.wait().map_err(|err| format!("Could not run {}: {err}", self.command))?;
+        if !status.success() {
+            return Err(format!("{} failed with {status}", self.command));
+        }
+        return Ok(());
+    }
+}
+
+pub fn emit_events(sinks: &mut [Box<dyn EventSink>], events: Vec<WatchEvent>) {
+    let at = Utc::now();
+    for event in events {
+        let event = TimedEvent { at, event };
+        for sink in sinks.iter_mut() {
+            if let Err(err) = sink.emit(&event) {
+                eprintln!("{err}");
+            }
+        }
+    }
+}
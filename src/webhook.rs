@@ -0,0 +1,230 @@
+use serde_json::{json, Value};
+
+use crate::{change_report::ChangeReport, http::{HttpClient, HttpSettings}, parsed_character::ParsedCharacter};
+
+//discord rejects embeds over these limits, the total counting the title and every field name and value
+const DISCORD_MAX_FIELDS: usize = 25;
+const DISCORD_MAX_FIELD_LENGTH: usize = 1024;
+const DISCORD_MAX_EMBED_LENGTH: usize = 6000;
+//kept back from the total for the description saying how many sections were left out
+const DISCORD_OMITTED_NOTE_LENGTH: usize = 64;
+//a field with less room than this for its value isn't worth sending
+const DISCORD_MIN_FIELD_VALUE: usize = 32;
+//slack rejects messages over these limits
+const SLACK_MAX_BLOCKS: usize = 50;
+const SLACK_MAX_HEADER_LENGTH: usize = 150;
+const SLACK_MAX_SECTION_LENGTH: usize = 3000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    //{character, id, sections, markdown}, for scripts and services of our own
+    Json,
+    Discord,
+    Slack,
+}
+
+impl WebhookFormat {
+    //picks the format from the webhook's host, falling back to generic json
+    pub fn detect(url: &str) -> Self {
+        if url.contains("discord.com/api/webhooks") || url.contains("discordapp.com/api/webhooks") {
+            return WebhookFormat::Discord;
+        }
+        if url.contains("hooks.slack.com") {
+            return WebhookFormat::Slack;
+        }
+        return WebhookFormat::Json;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+//posts a summary of every saved diff to the configured webhooks
+#[derive(Debug)]
+pub struct Notifier {
+    pub webhooks: Vec<Webhook>,
+    //print the payloads instead of sending them
+    pub dry_run: bool,
+    http: HttpClient,
+}
+
+impl Notifier {
+    pub fn new(webhooks: Vec<Webhook>, dry_run: bool, settings: HttpSettings) -> Self {
+        Notifier {
+            webhooks,
+            dry_run,
+            http: HttpClient::new(HttpSettings { cache: None, ..settings }),
+        }
+    }
+
+    //a failed webhook is reported, but doesn't undo or fail the save
+    pub async fn notify(&self, character: &ParsedCharacter, report: &ChangeReport) {
+        for webhook in &self.webhooks {
+            let body = payload(webhook.format, character, report);
+            if self.dry_run {
+                eprintln!("Would post to {}:\n{}", webhook.url, serde_json::to_string_pretty(&body).unwrap_or_default());
+                continue;
+            }
+            match self.http.post_json(&webhook.url, &body).await {
+                Ok(_) => eprintln!("Notified {}.", webhook.url),
+                Err(err) => eprintln!("Webhook failed: {err}"),
+            }
+        }
+    }
+}
+
+pub fn payload(format: WebhookFormat, character: &ParsedCharacter, report: &ChangeReport) -> Value {
+    match format {
+        WebhookFormat::Json => json_payload(character, report),
+        WebhookFormat::Discord => discord_payload(report),
+        WebhookFormat::Slack => slack_payload(report),
+    }
+}
+
+fn json_payload(character: &ParsedCharacter, report: &ChangeReport) -> Value {
+    let sections: Vec<Value> = report.sections.iter().map(|(section, changes)| json!({
+        "section": section,
+        "changes": changes,
+    })).collect();
+    return json!({
+        "character": character.name,
+        "id": character.id,
+        "sections": sections,
+        "markdown": report.to_markdown(),
+    });
+}
+
+fn discord_payload(report: &ChangeReport) -> Value {
+    let title = format!("Changes to {}", report.character);
+    let mut budget = DISCORD_MAX_EMBED_LENGTH.saturating_sub(title.chars().count() + DISCORD_OMITTED_NOTE_LENGTH);
+    let mut fields = Vec::<Value>::new();
+    for (section, changes) in report.sections.iter().take(DISCORD_MAX_FIELDS) {
+        let name_length = section.chars().count();
+        if budget < name_length + DISCORD_MIN_FIELD_VALUE {
+            break;
+        }
+        let value = bulleted(changes, DISCORD_MAX_FIELD_LENGTH.min(budget - name_length));
+        budget -= name_length + value.chars().count();
+        fields.push(json!({ "name": section, "value": value }));
+    }
+    let mut embed = json!({ "title": title, "fields": fields });
+    let omitted = report.sections.len() - fields.len();
+    if omitted > 0 {
+        embed["description"] = json!(format!("…and {omitted} more section(s) that didn't fit"));
+    }
+    return json!({ "embeds": [embed] });
+}
+
+fn slack_payload(report: &ChangeReport) -> Value {
+    let title = format!("Changes to {}", report.character);
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": shortened(&title, SLACK_MAX_HEADER_LENGTH) },
+    })];
+    //the header and the note about omitted sections take a block each
+    let shown = report.sections.len().min(SLACK_MAX_BLOCKS - 2);
+    for (section, changes) in &report.sections[..shown] {
+        let heading = format!("*{}*\n", shortened(section, SLACK_MAX_SECTION_LENGTH / 2));
+        let text = format!("{heading}{}", bulleted(changes, SLACK_MAX_SECTION_LENGTH - heading.chars().count()));
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text },
+        }));
+    }
+    let omitted = report.sections.len() - shown;
+    if omitted > 0 {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": format!("…and {omitted} more section(s) that didn't fit") }],
+        }));
+    }
+    //the text is what notifications show
+    return json!({ "text": title, "blocks": blocks });
+}
+
+//the text, cut to max_length characters with an ellipsis when it is longer
+fn shortened(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max_length - 1).collect();
+    cut.push('…');
+    return cut;
+}
+
+//"• line" per change, cut off with a count of the rest once it would exceed max_length
+fn bulleted(changes: &[String], max_length: usize) -> String {
+    let mut text = String::new();
+    for (shown, change) in changes.iter().enumerate() {
+        let line = format!("• {change}\n");
+        let rest = format!("…and {} more", changes.len() - shown);
+        if text.chars().count() + line.chars().count() + rest.chars().count() > max_length {
+            text.push_str(&rest);
+            return text;
+        }
+        text.push_str(&line);
+    }
+    return text.trim_end().to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{Arc, Mutex}, time::Duration};
+
+    use crate::test_server::{stand_in, Request};
+
+    use super::*;
+
+    fn embed_length(embed: &Value) -> usize {
+        let text = |value: &Value| value.as_str().map(|text| text.chars().count()).unwrap_or(0);
+        let fields: usize = embed["fields"].as_array().unwrap().iter().map(|field| text(&field["name"]) + text(&field["value"])).sum();
+        return text(&embed["title"]) + text(&embed["description"]) + fields;
+    }
+
+    #[test]
+    fn discord_embed_stays_within_total_limit() {
+        let sections = (0..20).map(|section| (format!("Section {section}"), (0..40).map(|line| format!("Skill {line}: multiplier changed from 10.00% to 12.50%")).collect())).collect();
+        let report = ChangeReport { character: "Shorekeeper".to_string(), sections };
+        let body = discord_payload(&report);
+        let embed = &body["embeds"][0];
+        assert!(embed_length(embed) <= DISCORD_MAX_EMBED_LENGTH);
+        assert!(embed["fields"].as_array().unwrap().iter().all(|field| field["value"].as_str().unwrap().chars().count() <= DISCORD_MAX_FIELD_LENGTH));
+        assert!(embed["description"].as_str().unwrap().contains("more section(s)"));
+    }
+
+    #[test]
+    fn slack_message_stays_within_limits() {
+        let sections = (0..80).map(|section| (format!("Section {section}"), (0..200).map(|line| format!("Skill {line}: multiplier changed from 10.00% to 12.50%")).collect())).collect();
+        let report = ChangeReport { character: "Shorekeeper ".repeat(20), sections };
+        let body = slack_payload(&report);
+        let blocks = body["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), SLACK_MAX_BLOCKS);
+        assert!(blocks[0]["text"]["text"].as_str().unwrap().chars().count() <= SLACK_MAX_HEADER_LENGTH);
+        let sections = blocks.iter().filter(|block| block["type"] == "section");
+        assert!(sections.clone().all(|block| block["text"]["text"].as_str().unwrap().chars().count() <= SLACK_MAX_SECTION_LENGTH));
+        assert_eq!(sections.count(), SLACK_MAX_BLOCKS - 2);
+        assert!(blocks.last().unwrap()["elements"][0]["text"].as_str().unwrap().contains("32 more section(s)"));
+    }
+
+    #[tokio::test]
+    async fn notifier_posts_and_retries_server_errors() {
+        let requests = Arc::new(Mutex::new(Vec::<Request>::new()));
+        let base = stand_in(vec![("503 Service Unavailable\r\nRetry-After: 0", ""), ("204 No Content", "")], requests.clone()).await;
+        let url = format!("{base}/webhook");
+        let settings = HttpSettings { requests_per_second: 0.0, retries: 2, timeout: Duration::from_secs(5), ..HttpSettings::default() };
+        let notifier = Notifier::new(vec![Webhook { url, format: WebhookFormat::Json }], false, settings);
+        let character = ParsedCharacter { id: 1505, name: "Shorekeeper".to_string(), ..ParsedCharacter::default() };
+        let report = ChangeReport { character: "Shorekeeper".to_string(), sections: vec![("Base Stats".to_string(), vec!["Base HP +1% (100→101)".to_string()])] };
+        notifier.notify(&character, &report).await;
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2, "the 503 should have been retried once");
+        for request in requests.iter() {
+            let posted: Value = serde_json::from_str(&request.body).unwrap();
+            assert_eq!(posted, payload(WebhookFormat::Json, &character, &report));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{game_terms::{element_label, weapon_label}, parsed_character::{format_at_levels, in_key_order, ParsedCharacter, SkillLarge, SkillVariant, FORMAT_LEVELS}};
+
+//MediaWiki template invocations for a character page: {{Resonator Infobox}}, a {{Skill}} per skill,
+//{{Resonance Chain}} and {{Ascension Materials}}; numbered params follow the rows they come from
+pub fn character_wikitext(character: &ParsedCharacter) -> String {
+    let mut params = vec![
+        ("name", character.name.clone()),
+        ("id", character.id.to_string()),
+        ("rarity", character.rarity.to_string()),
+        ("element", element_label(character.element)),
+        ("weapon", weapon_label(character.weapon)),
+        ("hp", character.stats.life.to_string()),
+        ("atk", character.stats.atk.to_string()),
+        ("def", character.stats.def.to_string()),
+    ];
+    let tags: Vec<&str> = character.tags.iter().map(|tag| tag.name.as_str()).collect();
+    params.push(("tags", tags.join(", ")));
+    let mut text = template("Resonator Infobox", params.into_iter().map(|(name, value)| (name.to_string(), value)).collect());
+
+    for (_, tree) in in_key_order(&character.skills) {
+        match &tree.skill {
+            SkillVariant::SkillL(skill) => text.push_str(&skill_template(skill)),
+            SkillVariant::SkillS(skill) => text.push_str(&template("Stat Bonus", vec![
+                ("name".to_string(), skill.name.clone()),
+                ("description".to_string(), skill.desc.clone()),
+            ])),
+            SkillVariant::None => {},
+        }
+    }
+
+    let mut chain_params = Vec::<(String, String)>::new();
+    for (position, (_, chain)) in in_key_order(&character.chains).into_iter().enumerate() {
+        chain_params.push((format!("chain{}_name", position + 1), chain.name.clone()));
+        chain_params.push((format!("chain{}_description", position + 1), chain.desc.clone()));
+    }
+    text.push_str(&template("Resonance Chain", chain_params));
+
+    let mut ascension_params = Vec::<(String, String)>::new();
+    for (stage, items) in in_key_order(&character.ascensions) {
+        for (position, (item, count)) in items.iter().enumerate() {
+            ascension_params.push((format!("stage{stage}_item{}", position + 1), item.clone()));
+            ascension_params.push((format!("stage{stage}_item{}_amount", position + 1), count.to_string()));
+        }
+    }
+    text.push_str(&template("Ascension Materials", ascension_params));
+    return text;
+}
+
+//Level rows become rowN_name/rowN_lv1/rowN_lv10, Damage entries damageN_* with the same level suffixes
+fn skill_template(skill: &SkillLarge) -> String {
+    let [first, last] = FORMAT_LEVELS;
+    let mut params = vec![
+        ("name".to_string(), skill.name.clone()),
+        ("type".to_string(), skill.type_field.clone()),
+        ("description".to_string(), skill.desc.clone()),
+    ];
+    for (position, (_, level)) in in_key_order(&skill.level).into_iter().enumerate() {
+        let row = format!("row{}", position + 1);
+        let [at_first, at_last] = format_at_levels(&level.format);
+        params.push((format!("{row}_name"), level.name.clone()));
+        params.push((format!("{row}_lv{first}"), at_first));
+        params.push((format!("{row}_lv{last}"), at_last));
+    }
+    for (position, (_, damage)) in in_key_order(&skill.damage).into_iter().enumerate() {
+        let entry = format!("damage{}", position + 1);
+        let [at_first, at_last] = format_at_levels(&damage.rate_lv);
+        params.push((format!("{entry}_multiplier_lv{first}"), at_first));
+        params.push((format!("{entry}_multiplier_lv{last}"), at_last));
+        params.push((format!("{entry}_element"), element_label(damage.element)));
+        params.push((format!("{entry}_property"), damage.related_property.clone()));
+        params.push((format!("{entry}_type"), damage.type_field.to_string()));
+        params.push((format!("{entry}_energy"), damage.energy.to_string()));
+        params.push((format!("{entry}_toughness"), damage.tough_lv.to_string()));
+        params.push((format!("{entry}_element_power"), damage.element_power.to_string()));
+        params.push((format!("{entry}_hardness"), damage.hardness_lv.to_string()));
+    }
+    return template("Skill", params);
+}
+
+fn template(name: &str, params: Vec<(String, String)>) -> String {
+    let mut text = format!("{{{{{name}\n");
+    for (param, value) in params {
+        text.push_str(&format!("|{param} = {}\n", escape(&value)));
+    }
+    text.push_str("}}\n");
+    return text;
+}
+
+//a bare pipe or braces would end the parameter or template early, or open a new one; braces are
+//entity-encoded before pipes become {{!}}, so the template that replaces a pipe stays intact
+fn escape(value: &str) -> String {
+    return value.replace("{{", "&#123;&#123;").replace("}}", "&#125;&#125;").replace('|', "{{!}}").replace('\n', "<br />");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_keeps_pipe_template_intact() {
+        assert_eq!(escape("a|b"), "a{{!}}b");
+        assert_eq!(escape("{{Foo}}|x"), "&#123;&#123;Foo&#125;&#125;{{!}}x");
+        assert_eq!(escape("line\nbreak"), "line<br />break");
+    }
+}